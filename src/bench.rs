@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::i18n::{MsgKey, t};
+
+/// 一次流式请求的耗时统计
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StreamStats {
+    /// 从发送请求到收到第一个字符（包括推理内容）的时间
+    pub first_token: Option<Duration>,
+    /// 从发送请求到数据流结束的时间
+    pub elapsed: Duration,
+    /// 收到的正文和推理内容字符数
+    pub chars: usize,
+    /// 接口返回的生成token数，不返回用量统计时为 None
+    pub completion_tokens: Option<u64>,
+}
+
+impl StreamStats {
+    /// 每秒输出的字符数
+    pub fn chars_per_sec(&self) -> f64 {
+        per_sec(self.chars as f64, self.elapsed)
+    }
+
+    /// 每秒生成的token数
+    pub fn tokens_per_sec(&self) -> Option<f64> {
+        self.completion_tokens.map(|tokens| per_sec(tokens as f64, self.elapsed))
+    }
+}
+
+fn per_sec(amount: f64, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+    amount / elapsed.as_secs_f64()
+}
+
+fn format_secs(duration: Option<Duration>) -> String {
+    duration.map_or_else(|| "-".to_string(), |d| format!("{:.3}s", d.as_secs_f64()))
+}
+
+fn format_rate(rate: Option<f64>) -> String {
+    rate.map_or_else(|| "-".to_string(), |rate| format!("{:.1}", rate))
+}
+
+/// 各项指标的平均值，没有任何一轮提供该指标时为 None
+fn average(runs: &[StreamStats], value: impl Fn(&StreamStats) -> Option<f64>) -> Option<f64> {
+    let values: Vec<f64> = runs.iter().filter_map(value).collect();
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// 输出每一轮的统计和平均值组成的表格
+pub fn summary_table(runs: &[StreamStats]) -> String {
+    let mut rows = vec![[
+        t(MsgKey::BenchRun).to_string(),
+        t(MsgKey::BenchFirstToken).to_string(),
+        t(MsgKey::BenchElapsed).to_string(),
+        t(MsgKey::BenchChars).to_string(),
+        t(MsgKey::BenchCharsPerSec).to_string(),
+        t(MsgKey::BenchTokensPerSec).to_string(),
+    ]];
+    for (index, run) in runs.iter().enumerate() {
+        rows.push([
+            (index + 1).to_string(),
+            format_secs(run.first_token),
+            format_secs(Some(run.elapsed)),
+            run.chars.to_string(),
+            format_rate(Some(run.chars_per_sec())),
+            format_rate(run.tokens_per_sec()),
+        ]);
+    }
+    if !runs.is_empty() {
+        rows.push([
+            t(MsgKey::BenchAverage).to_string(),
+            format_secs(average(runs, |run| run.first_token.map(|d| d.as_secs_f64())).map(Duration::from_secs_f64)),
+            format_secs(average(runs, |run| Some(run.elapsed.as_secs_f64())).map(Duration::from_secs_f64)),
+            format_rate(average(runs, |run| Some(run.chars as f64))),
+            format_rate(average(runs, |run| Some(run.chars_per_sec()))),
+            format_rate(average(runs, StreamStats::tokens_per_sec)),
+        ]);
+    }
+
+    // 按显示宽度对齐各列，表头可能包含中文
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| rows.iter().map(|row| row[column].width()).max().unwrap_or(0))
+        .collect();
+    let mut table = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row.iter().zip(&widths)
+            .map(|(cell, &width)| format!("{}{}", " ".repeat(width - cell.width()), cell))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
@@ -0,0 +1,127 @@
+use clap::Parser;
+
+/// 命令行ChatGPT客户端
+#[derive(Debug, Parser)]
+#[command(name = "hambur", version, about)]
+pub struct Cli {
+    /// 直接发送的问题，提供时以单次模式运行：回答输出到stdout，状态信息输出到stderr
+    pub prompt: Option<String>,
+
+    /// 单次模式的指令；stdin 被重定向时，读取的全部内容作为上下文与指令一起发送
+    #[arg(long, value_name = "INSTRUCTION", conflicts_with = "prompt")]
+    pub ask: Option<String>,
+
+    /// 单次模式：读取文件的全部内容作为问题发送
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["prompt", "ask"])]
+    pub prompt_file: Option<String>,
+
+    /// 读取文件的全部内容作为系统提示词，覆盖配置文件中的 `system_prompt`
+    #[arg(long, value_name = "PATH")]
+    pub system_prompt_file: Option<String>,
+
+    /// 批处理模式：从stdin逐行读取问题，每个非空行单独发送，回答之间以 `---` 分隔
+    #[arg(long, conflicts_with_all = ["prompt", "ask", "prompt_file"])]
+    pub stdin_loop: bool,
+
+    /// 配合 `--stdin-loop` 使用，各行共享同一份聊天记录
+    #[arg(long, requires = "stdin_loop")]
+    pub stateful: bool,
+
+    /// 配合 `--stdin-loop` 使用，最多同时进行的请求数；大于1时并发请求，
+    /// 回答不逐字显示，而是缓冲到完整后按输入顺序输出。钩子和备用模型照常生效
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "stdin_loop", conflicts_with = "stateful", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_concurrent: usize,
+
+    /// 测速模式：发送指定问题，统计首字延迟和每秒输出的字符数、token数，结束后输出汇总表格
+    #[arg(long, value_name = "PROMPT", conflicts_with_all = ["prompt", "ask", "prompt_file", "stdin_loop"])]
+    pub bench: Option<String>,
+
+    /// 测速模式下重复请求的次数，汇总表格中给出平均值
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "bench", value_parser = clap::value_parser!(u32).range(1..))]
+    pub bench_runs: u32,
+
+    /// 启动本地HTTP服务，在浏览器中打开 `http://<地址>/` 聊天，`POST /chat` 转发请求并返回SSE数据流
+    #[cfg(feature = "serve")]
+    #[arg(long, value_name = "ADDR", num_args = 0..=1, default_missing_value = "127.0.0.1:8080", conflicts_with_all = ["prompt", "ask", "prompt_file", "stdin_loop", "bench", "stream_to_file"])]
+    pub serve: Option<std::net::SocketAddr>,
+
+    /// 流式输出时每个字符之间的延迟（毫秒），0 表示关闭打字机效果
+    #[arg(long, value_name = "MS")]
+    pub delay: Option<u64>,
+
+    /// 将回复正文（不带颜色）写入指定外部命令的标准输入，例如 `--pipe "bat -l md"`
+    #[arg(long, value_name = "COMMAND")]
+    pub pipe: Option<String>,
+
+    /// 单次模式：回复正文直接写入指定文件，不在内存中累积，终端中只显示已接收的字符数，用于生成很长的内容
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["stdin_loop", "bench", "pipe"])]
+    pub stream_to_file: Option<String>,
+
+    /// 输出调试信息（耗时、解析错误等）
+    #[arg(long)]
+    pub debug: bool,
+
+    /// 在终端中渲染回复里的 `**粗体**`、`*斜体*` 和 `# 标题`
+    #[arg(long)]
+    pub markdown: bool,
+
+    /// 在stderr中原样显示收到的每一行SSE数据（以 `<<` 标记），用于排查提供商的格式问题
+    #[arg(long)]
+    pub raw: bool,
+
+    /// 每次请求只发送系统提示词和最近 N 条消息，完整的聊天记录仍保存在本地
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub context_window: Option<usize>,
+
+    /// 流式输出中途断开时的最大重连次数
+    #[arg(long, value_name = "N")]
+    pub reconnect: Option<u32>,
+
+    /// 使用配置文件中的指定配置档，覆盖 `HAMBUR_PROFILE`
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// 列出配置文件中的所有配置档后退出
+    #[arg(long)]
+    pub list_profiles: bool,
+
+    /// 显示当前生效的设置及其来源（默认值、环境变量、命令行参数或配置文件）和已启用的提供商后退出
+    #[arg(long)]
+    pub show_config: bool,
+
+    /// 列出通过 `/save` 保存的会话后退出
+    #[arg(long)]
+    pub list_sessions: bool,
+
+    /// 配合 `--list-sessions` 使用，只列出带有该标签的会话，可以重复指定
+    #[arg(long = "tag", value_name = "TAG", requires = "list_sessions")]
+    pub tags: Vec<String>,
+
+    /// 输出指定shell的补全脚本后退出，例如 `hambur --generate-completions zsh > _hambur`
+    #[arg(long, value_name = "SHELL")]
+    pub generate_completions: Option<clap_complete::Shell>,
+
+    /// 从各提供商的 /models 接口重新获取模型列表并更新缓存
+    #[arg(long)]
+    pub refresh_models: bool,
+
+    /// 不显示推理内容，推理期间显示 `[思考中...]` 提示
+    #[arg(long)]
+    pub hide_reasoning: bool,
+
+    /// 只输出推理内容（写入stdout），正文照常接收并保存但不显示，推理内容同时保存到聊天记录中
+    #[arg(long, conflicts_with_all = ["hide_reasoning", "pipe", "stream_to_file"])]
+    pub only_reasoning: bool,
+
+    /// 将推理内容随回复一起保存到聊天记录中（不会发送给API），`/save` 时一并导出
+    #[arg(long)]
+    pub keep_reasoning: bool,
+
+    /// 以当前模型的名称（如 `gemini-flash:`）代替 `AI:` 作为回复标签
+    #[arg(long)]
+    pub show_model_label: bool,
+
+    /// 在 `/history` 输出中为每条消息显示本地时间
+    #[arg(long)]
+    pub timestamps: bool,
+}
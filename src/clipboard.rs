@@ -0,0 +1,30 @@
+use anyhow::Result;
+
+use crate::i18n::{MsgKey, t};
+
+/// 读取剪贴板中的图片，编码为PNG格式的 data URL，剪贴板中没有图片时返回 None
+#[cfg(feature = "clipboard")]
+pub fn read_image() -> Result<Option<String>> {
+    use anyhow::Context;
+    use base64::Engine;
+
+    let mut clipboard = arboard::Clipboard::new().context(t(MsgKey::ClipboardUnavailable))?;
+    let image = match clipboard.get_image() {
+        Ok(image) => image,
+        Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+        Err(e) => return Err(e).context(t(MsgKey::ClipboardReadFailed)),
+    };
+
+    // arboard 返回未压缩的RGBA像素
+    let mut encoded = Vec::new();
+    let mut encoder = png::Encoder::new(&mut encoded, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&image.bytes)?;
+    Ok(Some(format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(encoded))))
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn read_image() -> Result<Option<String>> {
+    anyhow::bail!(t(MsgKey::ClipboardDisabled))
+}
@@ -0,0 +1,107 @@
+use crossterm::style::{Color, ContentStyle, ResetColor, SetForegroundColor, StyledContent, Stylize};
+use crossterm::Command;
+use std::fmt::{self, Display};
+use std::sync::OnceLock;
+
+/// 是否输出颜色和样式控制符
+/// 按 NO_COLOR 标准，设置了非空的 `NO_COLOR` 或 `TERM=dumb` 时关闭，只在第一次调用时检测
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+        !no_color && !dumb
+    })
+}
+
+/// 代替 `SetForegroundColor`，关闭颜色时不输出任何内容
+#[derive(Debug, Clone, Copy)]
+pub struct SetColor(pub Color);
+
+impl Command for SetColor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if enabled() {
+            SetForegroundColor(self.0).write_ansi(f)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        if enabled() {
+            SetForegroundColor(self.0).execute_winapi()?;
+        }
+        Ok(())
+    }
+}
+
+/// 代替 `ResetColor`，关闭颜色时不输出任何内容
+#[derive(Debug, Clone, Copy)]
+pub struct Reset;
+
+impl Command for Reset {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if enabled() {
+            ResetColor.write_ansi(f)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        if enabled() {
+            ResetColor.execute_winapi()?;
+        }
+        Ok(())
+    }
+}
+
+/// 代替 crossterm 的 `Stylize`，关闭颜色时返回不带样式的内容，输出时原样显示文本
+pub trait Paint: Sized {
+    type Styled: Stylize<Styled = Self::Styled>;
+
+    /// 转换为不带样式的内容
+    fn unstyled(self) -> Self::Styled;
+
+    fn paint(self, apply: fn(Self::Styled) -> Self::Styled) -> Self::Styled {
+        let content = self.unstyled();
+        if enabled() { apply(content) } else { content }
+    }
+
+    fn red(self) -> Self::Styled { self.paint(Stylize::red) }
+    fn green(self) -> Self::Styled { self.paint(Stylize::green) }
+    fn yellow(self) -> Self::Styled { self.paint(Stylize::yellow) }
+    fn blue(self) -> Self::Styled { self.paint(Stylize::blue) }
+    fn cyan(self) -> Self::Styled { self.paint(Stylize::cyan) }
+    fn dim(self) -> Self::Styled { self.paint(Stylize::dim) }
+    fn bold(self) -> Self::Styled { self.paint(Stylize::bold) }
+    fn reverse(self) -> Self::Styled { self.paint(Stylize::reverse) }
+
+    /// 指定前景色，用于颜色由调用方决定的场合
+    fn color(self, color: Color) -> Self::Styled {
+        let content = self.unstyled();
+        if enabled() { content.with(color) } else { content }
+    }
+}
+
+impl<D: Display> Paint for StyledContent<D> {
+    type Styled = Self;
+
+    fn unstyled(self) -> Self {
+        self
+    }
+}
+
+macro_rules! impl_paint {
+    ($($t:ty),*) => {$(
+        impl Paint for $t {
+            type Styled = StyledContent<Self>;
+
+            fn unstyled(self) -> Self::Styled {
+                StyledContent::new(ContentStyle::new(), self)
+            }
+        }
+    )*};
+}
+
+impl_paint!(&str, String, &String, char);
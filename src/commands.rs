@@ -0,0 +1,325 @@
+use crate::i18n::{MsgKey, t};
+
+/// 交互模式下用户输入解析后的命令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// 退出程序
+    Exit,
+    /// 清空聊天记录
+    Clear,
+    /// 切换编写模式（回车换行，/send 或 Ctrl+D 提交）
+    Compose,
+    /// 修改运行时设置
+    Set { key: String, value: String },
+    /// 显示可通过 `/set` 修改的设置
+    ShowSettings,
+    /// 切换采样预设，None 表示列出所有预设
+    Mode(Option<String>),
+    /// 显示当前生效的设置及其来源
+    Config,
+    /// 显示当前模型的详细信息
+    ModelInfo,
+    /// 显示发送给当前提供商的请求头
+    Headers,
+    /// 添加本次会话的请求头，value 为 None 表示删除
+    SetHeader { name: String, value: Option<String> },
+    /// 列出带序号的模型，可按关键字过滤
+    Models(String),
+    /// 按最近一次 `/models` 列表中的序号（从1开始）切换模型
+    SelectModel(usize),
+    /// 切换当前模型的收藏状态
+    Favorite,
+    /// 切换到指定提供商的默认模型，None 表示列出所有提供商
+    Provider(Option<String>),
+    /// 显示当前会话的聊天记录
+    History,
+    /// 显示保存的输入历史
+    InputHistory,
+    /// 清空保存的输入历史
+    HistoryClear,
+    /// 修改聊天记录中指定序号消息的角色
+    Role { index: usize, role: String },
+    /// 将聊天记录保存为Markdown文件，并在会话索引中记录标签
+    Save { path: Option<String>, tags: Vec<String> },
+    /// 将聊天记录以微调数据格式追加到JSONL文件
+    ExportJsonl(String),
+    /// 用ChatGPT导出的对话替换聊天记录，index 为对话在导出文件中的序号（从1开始）
+    ImportChatGpt { path: String, index: usize },
+    /// 固定一条随每次请求发送的消息，不受 `clear` 影响
+    Pin(String),
+    /// 按 `/pins` 列表中的序号（从1开始）取消固定
+    Unpin(usize),
+    /// 列出固定的消息
+    Pins,
+    /// 新建一个标签页（独立的聊天记录和模型）并切换过去，None 表示使用默认名称
+    TabNew(Option<String>),
+    /// 列出所有标签页
+    TabList,
+    /// 按 `/tab list` 列表中的序号（从1开始）切换标签页
+    TabSwitch(usize),
+    /// 切换流式输出和接收完整后一次性显示
+    Buffer,
+    /// 以打字机效果重新输出最后一条回复
+    Replay,
+    /// 调整流式输出速度，None 表示显示当前速度
+    Speed(Option<u64>),
+    /// 设置会话费用上限（美元），0 表示取消上限，None 表示显示当前费用和上限
+    Budget(Option<f64>),
+    /// 重新读取配置文件和模型缓存
+    Reload,
+    /// 设置下一条回复的预填充文本，None 表示取消
+    Prefill(Option<String>),
+    /// 续写上一条回复（例如因长度上限被截断的回复）
+    Continue,
+    /// 将之前的聊天记录压缩为摘要
+    Compact,
+    /// 附带一条只对本轮生效的临时系统指令发送消息
+    Once { instruction: String, message: String },
+    /// 将剪贴板中的图片随问题一起发送
+    ImageClip(String),
+    /// 将文件内容附加到问题之前发送
+    File { path: String, question: String },
+    /// `@模型关键字 消息`：只用匹配的模型发送这一条消息，不切换当前模型
+    ModelOverride { query: String, message: String },
+    /// 格式错误的命令，附带提示信息
+    Invalid(String),
+    /// 普通输入（模型关键字或聊天消息）
+    Message(String),
+}
+
+/// 解析一行用户输入
+/// 命令优先于模型关键字匹配，未识别的输入原样作为消息返回，优先级依次为：
+/// 1. `exit`、`clear`（忽略大小写，不带 `/` 前缀）
+/// 2. 以 `/` 开头的已知命令和 `@模型关键字 消息`，参数格式错误时返回 `Invalid`
+/// 3. 其余输入返回 `Message`，由调用方先尝试匹配模型关键字，再作为聊天消息发送
+///
+/// 该函数不依赖终端和网络，解析结果只由输入决定
+pub fn parse_command(input: &str) -> Command {
+    let input = input.trim();
+
+    if input.eq_ignore_ascii_case("exit") {
+        return Command::Exit;
+    }
+    if input.eq_ignore_ascii_case("clear") {
+        return Command::Clear;
+    }
+
+    let (name, args) = match input.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim()),
+        None => (input, ""),
+    };
+
+    match name {
+        "/speed" => {
+            if args.is_empty() {
+                Command::Speed(None)
+            } else {
+                match args.parse() {
+                    Ok(delay) => Command::Speed(Some(delay)),
+                    Err(_) => Command::Invalid(t(MsgKey::SpeedUsage).to_string()),
+                }
+            }
+        }
+        "/budget" => {
+            if args.is_empty() {
+                Command::Budget(None)
+            } else {
+                match args.trim_start_matches('$').parse::<f64>() {
+                    Ok(budget) if budget >= 0.0 => Command::Budget(Some(budget)),
+                    _ => Command::Invalid(t(MsgKey::BudgetUsage).to_string()),
+                }
+            }
+        }
+        "/buffer" => Command::Buffer,
+        "/replay" => Command::Replay,
+        "/pin" if args.is_empty() => Command::Invalid(t(MsgKey::PinUsage).to_string()),
+        "/pin" => Command::Pin(args.to_string()),
+        "/unpin" => match args.parse() {
+            Ok(index) if index >= 1 => Command::Unpin(index),
+            _ => Command::Invalid(t(MsgKey::UnpinUsage).to_string()),
+        },
+        "/pins" => Command::Pins,
+        "/tab" => {
+            let (sub, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+            let rest = rest.trim();
+            match sub {
+                "new" => Command::TabNew((!rest.is_empty()).then(|| rest.to_string())),
+                "list" if rest.is_empty() => Command::TabList,
+                "switch" => match rest.parse() {
+                    Ok(index) if index >= 1 => Command::TabSwitch(index),
+                    _ => Command::Invalid(t(MsgKey::TabUsage).to_string()),
+                },
+                _ => Command::Invalid(t(MsgKey::TabUsage).to_string()),
+            }
+        },
+        "/reload" | "/models-refresh" => Command::Reload,
+        "/compose" => Command::Compose,
+        "/history" => Command::History,
+        "/input-history" => Command::InputHistory,
+        "/history-clear" => Command::HistoryClear,
+        "/role" => match args.split_once(char::is_whitespace) {
+            Some((index, role)) => match index.parse() {
+                Ok(index) => Command::Role {
+                    index,
+                    role: role.trim().to_lowercase(),
+                },
+                Err(_) => Command::Invalid(t(MsgKey::RoleUsage).to_string()),
+            },
+            None => Command::Invalid(t(MsgKey::RoleUsage).to_string()),
+        },
+        "/save" => match parse_save_args(args) {
+            Some((path, tags)) => Command::Save { path, tags },
+            None => Command::Invalid(t(MsgKey::SaveUsage).to_string()),
+        },
+        "/export-jsonl" if args.is_empty() => Command::Invalid(t(MsgKey::ExportJsonlUsage).to_string()),
+        "/export-jsonl" => Command::ExportJsonl(args.to_string()),
+        "/import" => match args.split_once(char::is_whitespace) {
+            Some((format, path)) if format.eq_ignore_ascii_case("chatgpt") => {
+                let path = path.trim();
+                // 路径后可以跟 `#序号` 选择对话，路径本身可以包含空格和数字
+                match path.rsplit_once(char::is_whitespace).filter(|(_, index)| index.starts_with('#')) {
+                    Some((path, index)) => match index[1..].parse() {
+                        Ok(index) if index >= 1 => Command::ImportChatGpt { path: path.trim_end().to_string(), index },
+                        _ => Command::Invalid(t(MsgKey::ImportUsage).to_string()),
+                    },
+                    None => Command::ImportChatGpt { path: path.to_string(), index: 1 },
+                }
+            },
+            _ => Command::Invalid(t(MsgKey::ImportUsage).to_string()),
+        },
+        "/set" if args.is_empty() => Command::ShowSettings,
+        "/set" => match args.split_once(char::is_whitespace) {
+            Some((key, value)) => Command::Set {
+                key: key.to_string(),
+                value: value.trim().to_string(),
+            },
+            None => Command::Invalid(t(MsgKey::SetUsage).to_string()),
+        },
+        "/config" => Command::Config,
+        "/model-info" => Command::ModelInfo,
+        "/headers" => Command::Headers,
+        "/set-header" if args.is_empty() => Command::Invalid(t(MsgKey::SetHeaderUsage).to_string()),
+        "/set-header" => match args.split_once(char::is_whitespace) {
+            Some((name, value)) => Command::SetHeader {
+                name: name.to_string(),
+                value: Some(value.trim().to_string()),
+            },
+            None => Command::SetHeader {
+                name: args.to_string(),
+                value: None,
+            },
+        },
+        "/mode" if args.is_empty() => Command::Mode(None),
+        "/mode" => Command::Mode(Some(args.to_string())),
+        "/models" => Command::Models(args.to_string()),
+        "/model" => match args.parse() {
+            Ok(index) if index >= 1 => Command::SelectModel(index),
+            _ => Command::Invalid(t(MsgKey::ModelIndexUsage).to_string()),
+        },
+        "/fav" => Command::Favorite,
+        "/provider" if args.is_empty() => Command::Provider(None),
+        "/provider" => Command::Provider(Some(args.to_string())),
+        "/prefill" if args.is_empty() => Command::Prefill(None),
+        "/prefill" => Command::Prefill(Some(args.to_string())),
+        "/continue" => Command::Continue,
+        "/compact" => Command::Compact,
+        "/once" => match args.split_once('|') {
+            Some((instruction, message)) if !instruction.trim().is_empty() && !message.trim().is_empty() => Command::Once {
+                instruction: instruction.trim().to_string(),
+                message: message.trim().to_string(),
+            },
+            _ => Command::Invalid(t(MsgKey::OnceUsage).to_string()),
+        },
+        "/image-clip" if args.is_empty() => Command::Invalid(t(MsgKey::ImageClipUsage).to_string()),
+        "/image-clip" => Command::ImageClip(args.to_string()),
+        "/file" => match args.split_once(char::is_whitespace) {
+            Some((path, question)) => Command::File {
+                path: path.to_string(),
+                question: question.trim().to_string(),
+            },
+            None if !args.is_empty() => Command::File {
+                path: args.to_string(),
+                question: String::new(),
+            },
+            None => Command::Invalid(t(MsgKey::FileUsage).to_string()),
+        },
+        _ if name.len() > 1 && name.starts_with('@') => {
+            if args.is_empty() {
+                Command::Invalid(t(MsgKey::ModelOverrideUsage).to_string())
+            } else {
+                Command::ModelOverride {
+                    query: name[1..].to_string(),
+                    message: args.to_string(),
+                }
+            }
+        },
+        _ => Command::Message(input.to_string()),
+    }
+}
+
+/// 解析 `/save <路径> [--tag <标签>]...`，路径中可以包含空格
+fn parse_save_args(args: &str) -> Option<(Option<String>, Vec<String>)> {
+    let (path, options) = match args.find(" --tag") {
+        _ if args.starts_with("--tag") => ("", args),
+        Some(start) => args.split_at(start),
+        None => (args, ""),
+    };
+    let path = path.trim();
+
+    let mut tags = Vec::new();
+    let mut tokens = options.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match (token, tokens.next()) {
+            ("--tag", Some(tag)) => tags.push(tag.to_string()),
+            _ => return None,
+        }
+    }
+    Some(((!path.is_empty()).then(|| path.to_string()), tags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(key: MsgKey) -> Command {
+        Command::Invalid(t(key).to_string())
+    }
+
+    #[test]
+    fn parses_commands_by_precedence() {
+        let cases = [
+            ("exit", Command::Exit),
+            ("EXIT", Command::Exit),
+            (" clear ", Command::Clear),
+            // 未识别的 `/` 命令原样作为消息返回
+            ("/help", Command::Message("/help".to_string())),
+            ("/save foo.md", Command::Save { path: Some("foo.md".to_string()), tags: vec![] }),
+            ("/save a b.md --tag x", Command::Save { path: Some("a b.md".to_string()), tags: vec!["x".to_string()] }),
+            ("/save", Command::Save { path: None, tags: vec![] }),
+            ("/save --tag", invalid(MsgKey::SaveUsage)),
+            ("/speed fast", invalid(MsgKey::SpeedUsage)),
+            ("/role 3 System", Command::Role { index: 3, role: "system".to_string() }),
+            // 带 `/` 前缀的命令优先，不带前缀的同名输入交给调用方作为模型关键字匹配
+            ("/compact", Command::Compact),
+            ("compact", Command::Message("compact".to_string())),
+            ("flash", Command::Message("flash".to_string())),
+            ("/unpin 0", invalid(MsgKey::UnpinUsage)),
+            ("/unpin 2", Command::Unpin(2)),
+            ("/tab switch 0", invalid(MsgKey::TabUsage)),
+            ("/tab switch 2", Command::TabSwitch(2)),
+            ("/tab new work", Command::TabNew(Some("work".to_string()))),
+            ("/import chatgpt ~/My Export 2", Command::ImportChatGpt { path: "~/My Export 2".to_string(), index: 1 }),
+            ("/import chatgpt a b.json #3", Command::ImportChatGpt { path: "a b.json".to_string(), index: 3 }),
+            ("/import chatgpt file.json #0", invalid(MsgKey::ImportUsage)),
+            ("/import chatgpt file.json #x", invalid(MsgKey::ImportUsage)),
+            ("/model 0", invalid(MsgKey::ModelIndexUsage)),
+            ("/model 3", Command::SelectModel(3)),
+            ("@gpt hi", Command::ModelOverride { query: "gpt".to_string(), message: "hi".to_string() }),
+            ("@gpt", invalid(MsgKey::ModelOverrideUsage)),
+            ("hello, how are you?", Command::Message("hello, how are you?".to_string())),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_command(input), expected, "input: {:?}", input);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+use crate::i18n::{MsgKey, tf};
+use crate::models::ModelProvider;
+use crate::paths;
+
+/// 配置文件内容
+/// 默认位于 `~/.config/hambur/config.json`，可通过 `HAMBUR_CONFIG` 指定其他路径
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// 自定义或覆盖内置的提供商
+    #[serde(default)]
+    pub providers: Vec<ModelProvider>,
+    /// 启动时使用的模型ID，未设置时使用内置的默认模型
+    #[serde(default)]
+    pub default_model: Option<String>,
+    /// 每次请求前附加的系统提示词
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// 只启用这些名称的提供商，未设置时启用全部
+    #[serde(default)]
+    pub enabled_providers: Option<Vec<String>>,
+    /// 自定义的用户提示符，默认为 `你:`
+    #[serde(default)]
+    pub user_label: Option<String>,
+    /// 自定义的回复标签，默认为 `AI:`
+    #[serde(default)]
+    pub assistant_label: Option<String>,
+    /// 命名配置档，通过 `--profile` 或 `HAMBUR_PROFILE` 选择
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// 自定义或覆盖内置的采样预设，通过 `/mode <名称>` 切换
+    #[serde(default)]
+    pub modes: BTreeMap<String, SamplingPreset>,
+    /// 发送消息前和收到回复后执行的外部命令
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// 备用模型（模型ID -> 备用模型ID），请求因网络错误、5xx 或 429 失败时改用备用模型重新发送，可以串联
+    #[serde(default)]
+    pub fallbacks: BTreeMap<String, String>,
+}
+
+/// 处理消息的外部命令，通过系统shell执行，内容写入标准输入，标准输出作为处理后的内容
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Hooks {
+    /// 处理发送前的用户消息
+    #[serde(default)]
+    pub pre_send: Option<String>,
+    /// 处理收到的完整回复
+    #[serde(default)]
+    pub post_receive: Option<String>,
+}
+
+/// 采样参数预设，未设置的参数使用提供商的默认值
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct SamplingPreset {
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub top_p: Option<f64>,
+}
+
+/// 配置档：选中时覆盖基础配置中的对应字段
+#[derive(Debug, Default, Deserialize)]
+pub struct Profile {
+    #[serde(default)]
+    pub default_model: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub enabled_providers: Option<Vec<String>>,
+    /// 与基础配置中同名的提供商会被整体替换，其余的追加到列表中
+    #[serde(default)]
+    pub providers: Vec<ModelProvider>,
+    /// 只覆盖配置档中设置了的钩子
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// 与基础配置中的备用模型合并，同一模型以配置档为准
+    #[serde(default)]
+    pub fallbacks: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// 配置文件路径
+    pub fn path() -> Option<PathBuf> {
+        match env::var("HAMBUR_CONFIG") {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => paths::config_dir().map(|dir| dir.join("config.json")),
+        }
+    }
+
+    /// 读取配置文件并合并选中的配置档，文件不存在时返回默认配置
+    pub fn load(profile: Option<&str>) -> Result<Self> {
+        let mut config = Self::load_file()?;
+        if let Some(name) = profile {
+            let profile = config.profiles.remove(name)
+                .context(tf(MsgKey::ProfileNotFound, &[&name]))?;
+            config.apply_profile(profile);
+        }
+        Ok(config)
+    }
+
+    fn load_file() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = std::fs::read_to_string(&path)
+            .context(tf(MsgKey::ConfigReadFailed, &[&path.display()]))?;
+        serde_json::from_str(&text)
+            .context(tf(MsgKey::ConfigParseFailed, &[&path.display()]))
+    }
+
+    /// 配置文件中定义的配置档名称
+    pub fn profile_names() -> Result<Vec<String>> {
+        Ok(Self::load_file()?.profiles.into_keys().collect())
+    }
+
+    fn apply_profile(&mut self, profile: Profile) {
+        if profile.default_model.is_some() {
+            self.default_model = profile.default_model;
+        }
+        if profile.system_prompt.is_some() {
+            self.system_prompt = profile.system_prompt;
+        }
+        if profile.enabled_providers.is_some() {
+            self.enabled_providers = profile.enabled_providers;
+        }
+        if profile.hooks.pre_send.is_some() {
+            self.hooks.pre_send = profile.hooks.pre_send;
+        }
+        if profile.hooks.post_receive.is_some() {
+            self.hooks.post_receive = profile.hooks.post_receive;
+        }
+        self.fallbacks.extend(profile.fallbacks);
+        for provider in profile.providers {
+            match self.providers.iter_mut().find(|p| p.name == provider.name) {
+                Some(existing) => *existing = provider,
+                None => self.providers.push(provider),
+            }
+        }
+    }
+}
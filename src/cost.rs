@@ -0,0 +1,16 @@
+use std::sync::Mutex;
+
+/// 本次会话累计的估算费用（美元）
+static SESSION_COST: Mutex<f64> = Mutex::new(0.0);
+
+/// 累加一次回复的估算费用
+pub fn record(cost: f64) {
+    if let Ok(mut total) = SESSION_COST.lock() {
+        *total += cost;
+    }
+}
+
+/// 本次会话累计的估算费用
+pub fn session_total() -> f64 {
+    SESSION_COST.lock().map(|total| *total).unwrap_or(0.0)
+}
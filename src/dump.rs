@@ -0,0 +1,35 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+use reqwest::header::HeaderMap;
+
+use crate::headers;
+use crate::i18n::{MsgKey, t};
+
+/// 将一次请求以一行JSON的形式追加到文件中，用于事后排查提供商的问题
+/// 记录时间、地址、请求头和完整的请求体，认证相关的请求头只保留名称
+pub fn append_request(path: &Path, url: &str, headers: &HeaderMap, body: &serde_json::Value) -> io::Result<()> {
+    let headers: serde_json::Map<String, serde_json::Value> = headers.iter()
+        .map(|(name, value)| {
+            let value = if headers::is_secret(name.as_str()) {
+                t(MsgKey::DumpRedacted).to_string()
+            } else {
+                value.to_str().unwrap_or_default().to_string()
+            };
+            (name.to_string(), value.into())
+        })
+        .collect();
+    let entry = serde_json::json!({
+        "time": chrono::Local::now().to_rfc3339(),
+        "url": url,
+        "headers": headers,
+        "body": body,
+    });
+
+    if let Some(dir) = path.parent() && !dir.as_os_str().is_empty() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry)
+}
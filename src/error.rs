@@ -0,0 +1,125 @@
+use std::fmt;
+
+use crate::i18n::{MsgKey, t, tf};
+
+/// 一轮对话请求失败的原因
+/// 由 `send_chat_request` 作为错误返回，提示文字在调用处通过 `Display` 输出
+#[derive(Debug)]
+pub enum ChatError {
+    /// 认证失败（401），附带存放密钥的环境变量名和响应内容
+    Auth { api_key_env: String, body: String },
+    /// 超出速率限制（429）
+    RateLimited { body: String },
+    /// 其他非成功的HTTP状态码
+    Http { status: u16, body: String },
+    /// 网络错误：连接失败或数据流中途断开
+    Network(reqwest::Error),
+    /// 重定向次数超过上限或出现循环，附带最后请求的地址
+    TooManyRedirects { url: String },
+    /// 关闭了自动跟随重定向时收到的3xx响应，附带重定向的目标地址
+    Redirect { status: u16, location: String },
+    /// 无法解析的响应数据
+    Parse { message: String, data: String },
+    /// 提供商在数据流中以错误帧的形式返回的错误
+    Api(String),
+    /// 用户按ESC中断了输出
+    Interrupted,
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ChatError::Auth { api_key_env, body } => tf(MsgKey::ErrAuth, &[api_key_env, body]),
+            ChatError::RateLimited { body } => tf(MsgKey::ErrRateLimited, &[body]),
+            ChatError::Http { status, body } => {
+                let reason = reqwest::StatusCode::from_u16(*status)
+                    .ok()
+                    .and_then(|s| s.canonical_reason())
+                    .unwrap_or(t(MsgKey::ErrUnknownStatus));
+                tf(MsgKey::ErrHttp, &[status, &reason, body])
+            },
+            ChatError::Network(e) => tf(MsgKey::ErrNetwork, &[e]),
+            ChatError::TooManyRedirects { url } => tf(MsgKey::ErrTooManyRedirects, &[url]),
+            ChatError::Redirect { status, location } => tf(MsgKey::ErrRedirect, &[status, location]),
+            ChatError::Parse { message, data } => tf(MsgKey::ErrParse, &[message, data]),
+            ChatError::Api(message) => tf(MsgKey::ErrApi, &[message]),
+            ChatError::Interrupted => t(MsgKey::Interrupted).to_string(),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for ChatError {}
+
+impl ChatError {
+    /// 根据发送请求时的错误构造，区分重定向错误和其他网络错误
+    pub fn from_request_error(error: reqwest::Error) -> Self {
+        if error.is_redirect() {
+            let url = error.url().map(|url| url.to_string()).unwrap_or_default();
+            return ChatError::TooManyRedirects { url };
+        }
+        ChatError::Network(error)
+    }
+
+    /// 可能只是暂时的故障（网络错误、5xx、429），换一个提供商有可能成功
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ChatError::Network(_) | ChatError::RateLimited { .. } => true,
+            ChatError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// 根据HTTP状态码和响应内容构造错误
+    pub fn from_status(status: u16, body: String, api_key_env: &str) -> Self {
+        match status {
+            401 => ChatError::Auth { api_key_env: api_key_env.to_string(), body },
+            429 => ChatError::RateLimited { body },
+            _ => ChatError::Http { status, body },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ErrorResponse;
+
+    #[test]
+    fn parses_openai_style_error_body() {
+        let body = r#"{"error":{"message":"Rate limit reached","code":429}}"#;
+        let error: ErrorResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(error.error.message, "Rate limit reached");
+        assert_eq!(error.error.to_string(), "Rate limit reached (429)");
+
+        let error: ErrorResponse = serde_json::from_str(r#"{"error":{"message":"Upstream failed"}}"#).unwrap();
+        assert_eq!(error.error.to_string(), "Upstream failed");
+    }
+
+    #[test]
+    fn plain_text_body_is_kept_as_is() {
+        let body = "Bad Gateway";
+        assert!(serde_json::from_str::<ErrorResponse>(body).is_err());
+        match ChatError::from_status(502, body.to_string(), "OPENAI_API_KEY") {
+            ChatError::Http { status, body } => {
+                assert_eq!(status, 502);
+                assert_eq!(body, "Bad Gateway");
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn maps_status_to_variant() {
+        let error = ChatError::from_status(401, String::new(), "OPENAI_API_KEY");
+        assert!(matches!(&error, ChatError::Auth { api_key_env, .. } if api_key_env == "OPENAI_API_KEY"));
+        assert!(!error.is_transient());
+
+        let error = ChatError::from_status(429, String::new(), "OPENAI_API_KEY");
+        assert!(matches!(error, ChatError::RateLimited { .. }));
+        assert!(error.is_transient());
+
+        assert!(ChatError::from_status(503, String::new(), "OPENAI_API_KEY").is_transient());
+        assert!(!ChatError::from_status(400, String::new(), "OPENAI_API_KEY").is_transient());
+    }
+}
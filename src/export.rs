@@ -0,0 +1,63 @@
+use crate::i18n::{MsgKey, t};
+use crate::models::ChatMessage;
+
+/// 将聊天记录转换为Markdown，每条消息一个二级标题，保存的推理内容放在可折叠的区块中
+pub fn to_markdown(message_history: &[ChatMessage]) -> String {
+    let mut markdown = String::new();
+
+    for message in message_history {
+        let heading = match message.role.as_str() {
+            "user" => t(MsgKey::SaveUserHeading),
+            "assistant" => t(MsgKey::SaveAssistantHeading),
+            role => role,
+        };
+        markdown.push_str(&format!("## {}\n\n", heading));
+
+        if let Some(reasoning) = &message.reasoning {
+            markdown.push_str(&format!(
+                "<details>\n<summary>{}</summary>\n\n{}\n\n</details>\n\n",
+                t(MsgKey::SaveReasoningSummary),
+                reasoning.trim_end()
+            ));
+        }
+        markdown.push_str(message.content.trim_end());
+        markdown.push_str("\n\n");
+    }
+
+    markdown
+}
+
+/// 将标题转换为可以用作文件名的形式：保留字母、数字（包括中文），其余字符替换为 `-`，最长50个字符
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= 50 {
+            break;
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// 将聊天记录转换为OpenAI微调数据格式的一行JSON：`{"messages": [...]}`，末尾带换行
+/// 系统提示词作为第一条消息；聊天记录中没有至少一条user消息和一条assistant消息时返回 None
+pub fn to_finetune_jsonl(message_history: &[ChatMessage], system_prompt: Option<&str>) -> Option<String> {
+    let has_role = |role: &str| message_history.iter().any(|m| m.role == role);
+    if !has_role("user") || !has_role("assistant") {
+        return None;
+    }
+
+    let mut messages: Vec<serde_json::Value> = Vec::new();
+    if let Some(system_prompt) = system_prompt {
+        messages.push(serde_json::json!({ "role": "system", "content": system_prompt }));
+    }
+    messages.extend(message_history.iter().map(|m| serde_json::json!({ "role": m.role, "content": m.content })));
+
+    let mut line = serde_json::json!({ "messages": messages }).to_string();
+    line.push('\n');
+    Some(line)
+}
@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+
+use crate::i18n::{MsgKey, tf};
+use crate::models::{ApiStyle, ModelProvider};
+
+/// Gemini 原生接口的认证请求头
+const GEMINI_API_KEY: &str = "x-goog-api-key";
+
+/// 发送给提供商的默认请求头：Bearer 认证和JSON内容类型
+/// Gemini 原生接口的密钥改用 `x-goog-api-key` 请求头发送，不放在请求地址中，以免随错误信息显示
+pub fn build_headers(api_key: &str, provider: &ModelProvider) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    let (name, value) = match provider.api_style {
+        ApiStyle::Gemini => (HeaderName::from_static(GEMINI_API_KEY), api_key.to_string()),
+        _ => (AUTHORIZATION, format!("Bearer {}", api_key)),
+    };
+    let mut value = HeaderValue::from_str(&value)
+        .context(tf(MsgKey::ApiKeyInvalidHeader, &[&provider.name]))?;
+    value.set_sensitive(true);
+    headers.insert(name, value);
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Ok(headers)
+}
+
+/// 加入通过 `/set-header` 添加的请求头，与已有的请求头同名时覆盖
+pub fn insert_extra(headers: &mut HeaderMap, extra: &[(String, String)]) -> Result<()> {
+    for (name, value) in extra {
+        headers.insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value)?);
+    }
+    Ok(())
+}
+
+/// 可能包含认证信息的请求头，显示和记录时隐藏其值
+pub fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name == "authorization" || name.contains("key") || name.contains("token")
+}
+
+/// 名称和值能否组成合法的请求头
+pub fn is_valid(name: &str, value: &str) -> bool {
+    HeaderName::from_bytes(name.as_bytes()).is_ok() && HeaderValue::from_str(value).is_ok()
+}
+
+/// 用于显示的请求头的值，认证信息只保留密钥开头和结尾的几个字符，例如 `Bearer sk-...abcd`
+pub fn display_value(name: &HeaderName, value: &HeaderValue) -> String {
+    let value = value.to_str().unwrap_or_default();
+    if name != AUTHORIZATION && name.as_str() != GEMINI_API_KEY {
+        return value.to_string();
+    }
+
+    let (scheme, secret) = value.split_once(' ').unwrap_or(("", value));
+    let chars: Vec<char> = secret.chars().collect();
+    let redacted = if chars.len() >= 12 {
+        format!("{}...{}", chars[..3].iter().collect::<String>(), chars[chars.len() - 4..].iter().collect::<String>())
+    } else {
+        "***".to_string()
+    };
+    if scheme.is_empty() { redacted } else { format!("{} {}", scheme, redacted) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(api_style: &str) -> ModelProvider {
+        serde_json::from_value(serde_json::json!({
+            "name": "test",
+            "api_base": "http://localhost/v1",
+            "api_key_env": "TEST_KEY",
+            "api_style": api_style,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn builds_bearer_and_json_headers() {
+        let headers = build_headers("sk-test", &provider("chat")).unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Bearer sk-test");
+        assert!(headers[AUTHORIZATION].is_sensitive());
+        assert_eq!(headers[CONTENT_TYPE], "application/json");
+
+        // Gemini 原生接口不使用Bearer认证
+        let headers = build_headers("AIza-test", &provider("gemini")).unwrap();
+        assert!(headers.get(AUTHORIZATION).is_none());
+        assert_eq!(headers[GEMINI_API_KEY], "AIza-test");
+        assert_eq!(headers[CONTENT_TYPE], "application/json");
+
+        // 无法放入请求头的密钥返回错误而不是panic
+        assert!(build_headers("bad\nkey", &provider("chat")).is_err());
+    }
+
+    #[test]
+    fn extra_headers_override_defaults_in_order() {
+        let mut headers = build_headers("sk-test", &provider("chat")).unwrap();
+        let extra = [
+            ("X-Title".to_string(), "first".to_string()),
+            ("Content-Type".to_string(), "text/plain".to_string()),
+            ("x-title".to_string(), "second".to_string()),
+        ];
+        insert_extra(&mut headers, &extra).unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Bearer sk-test");
+        assert_eq!(headers[CONTENT_TYPE], "text/plain");
+        assert_eq!(headers["x-title"], "second");
+        assert_eq!(headers.len(), 3);
+    }
+
+    #[test]
+    fn detects_secret_headers() {
+        for name in ["Authorization", "x-api-key", "X-Goog-Api-Key", "X-Auth-Token"] {
+            assert!(is_secret(name), "{}", name);
+        }
+        for name in ["Content-Type", "HTTP-Referer", "X-Title"] {
+            assert!(!is_secret(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn redacts_authorization_values() {
+        let headers = build_headers("sk-1234567890abcd", &provider("chat")).unwrap();
+        assert_eq!(display_value(&AUTHORIZATION, &headers[AUTHORIZATION]), "Bearer sk-...abcd");
+
+        // 少于12个字符的密钥整体隐藏
+        let headers = build_headers("short-key", &provider("chat")).unwrap();
+        assert_eq!(display_value(&AUTHORIZATION, &headers[AUTHORIZATION]), "Bearer ***");
+
+        let headers = build_headers("AIza1234567890xyz", &provider("gemini")).unwrap();
+        let name = HeaderName::from_static(GEMINI_API_KEY);
+        assert_eq!(display_value(&name, &headers[&name]), "AIz...0xyz");
+        assert!(headers.get(AUTHORIZATION).is_none());
+
+        assert_eq!(display_value(&CONTENT_TYPE, &headers[CONTENT_TYPE]), "application/json");
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::i18n::{MsgKey, t};
+
+/// 通过系统shell执行钩子命令：内容写入标准输入，返回标准输出（去掉末尾的换行）
+/// 命令启动失败、以非零状态码退出或没有输出时返回错误，由调用方改用原始内容
+pub fn run(command: &str, input: &str) -> Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    let mut child = shell
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context(t(MsgKey::HookSpawnFailed))?;
+
+    // 在单独的线程中写入输入，同时读取输出：回显输入的命令（如 `cat`）在内容超过管道缓冲区时
+    // 会因为输出没人读取而阻塞；命令不读取输入就退出时忽略写入错误，以退出状态为准
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = input.to_string();
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        })
+    });
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    if !output.status.success() {
+        bail!("{}", output.status);
+    }
+
+    let text = String::from_utf8(output.stdout).context(t(MsgKey::HookInvalidUtf8))?;
+    let text = text.trim_end_matches(['\r', '\n']);
+    if text.is_empty() {
+        bail!(t(MsgKey::HookNoOutput));
+    }
+    Ok(text.to_string())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn echoes_input_larger_than_pipe_buffer() {
+        let input = "0123456789abcdef\n".repeat(64 * 1024);
+        let output = run("cat", &input).unwrap();
+        assert_eq!(output, input.trim_end());
+    }
+
+    #[test]
+    fn fails_on_non_zero_exit_or_empty_output() {
+        assert!(run("exit 3", "text").is_err());
+        assert!(run("cat > /dev/null", "text").is_err());
+    }
+}
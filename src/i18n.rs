@@ -0,0 +1,518 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::LazyLock;
+
+/// 界面语言
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    Zh,
+    En,
+}
+
+/// 通过 `HAMBUR_LANG=en|zh` 选择，默认中文
+static LANG: LazyLock<Lang> = LazyLock::new(|| match env::var("HAMBUR_LANG") {
+    Ok(lang) if lang.to_lowercase().starts_with("en") => Lang::En,
+    _ => Lang::Zh,
+});
+
+/// 界面文字的键，带 `{}` 的文字需要通过 `tf` 填入参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MsgKey {
+    Welcome,
+    UserPrompt,
+    AssistantLabel,
+    DoubleEscExit,
+    Thinking,
+    CtrlCExit,
+    AutosaveDone,
+    AutosaveFailed,
+    DumpRequestFailed,
+    ConfirmClear,
+    ConfirmImport,
+    ImageClipUsage,
+    ClipboardNoImage,
+    ClipboardFailed,
+    ImportUsage,
+    ImportFailed,
+    ImportEmpty,
+    ImportSkipped,
+    ImportDone,
+    HistoryCleared,
+    InputHistoryEmpty,
+    ConfirmInputHistoryClear,
+    InputHistoryCleared,
+    InputHistoryClearFailed,
+    IdleCleared,
+    HistoryEmpty,
+    ComposeOn,
+    ComposeOff,
+    SaveUsage,
+    RoleUsage,
+    RoleIndexOutOfRange,
+    RoleInvalid,
+    RoleChanged,
+    HistorySaved,
+    SaveFailed,
+    SessionIndexFailed,
+    ExportJsonlUsage,
+    ExportJsonlIncomplete,
+    ExportJsonlAppended,
+    SaveUserHeading,
+    SaveAssistantHeading,
+    SaveReasoningSummary,
+    SpeedSet,
+    BufferOn,
+    PinUsage,
+    UnpinUsage,
+    PinAdded,
+    PinRemoved,
+    PinIndexOutOfRange,
+    PinsEmpty,
+    PinSaveFailed,
+    BufferOff,
+    Receiving,
+    SpeedCurrent,
+    SpeedUsage,
+    Reloaded,
+    CurrentModelMissing,
+    ReloadFailed,
+    PrefillSet,
+    PrefillCleared,
+    PrefillUnsupported,
+    PipeSpawnFailed,
+    PipeWriteFailed,
+    PipeExitStatus,
+    TitleGenerating,
+    TitleInstruction,
+    TitleFailed,
+    ReplayEmpty,
+    StreamFileFailed,
+    StreamFileProgress,
+    StreamFileDone,
+    StreamFileOneShot,
+    HookFailed,
+    HookProcessed,
+    ReplyTruncated,
+    ContinueNothing,
+    ContinueInstruction,
+    CompactNothing,
+    CompactInstruction,
+    CompactConfirm,
+    CompactSummary,
+    CompactDone,
+    AutoCompacting,
+    AutoCompactFailed,
+    ModelSubstituted,
+    ModelSwitched,
+    ModelOverride,
+    ModelOverrideUsage,
+    ModelOverrideNotFound,
+    TabUsage,
+    TabDefaultName,
+    TabCreated,
+    TabSwitched,
+    TabIndexOutOfRange,
+    TabListEntry,
+    ModelIndexUsage,
+    ModelIndexOutOfRange,
+    ModelListEmpty,
+    NoModelListing,
+    FavoriteAdded,
+    FavoriteRemoved,
+    FavoriteSaveFailed,
+    #[cfg(feature = "serve")]
+    ServeListening,
+    #[cfg(feature = "serve")]
+    ServeRequest,
+    #[cfg(feature = "serve")]
+    ServeForbidden,
+    ProviderNotFound,
+    ProviderNoModels,
+    ModelInfoTitle,
+    WaitingForReady,
+    ReadyTimeout,
+    SetHeaderUsage,
+    HeaderSet,
+    HeaderRemoved,
+    HeaderInvalid,
+    InfoId,
+    InfoName,
+    InfoProvider,
+    InfoApiBase,
+    InfoKeyEnv,
+    KeySet,
+    KeyUnset,
+    MultipleModels,
+    MenuFilter,
+    MenuNoMatch,
+    ModelSwitchCancelled,
+    FileUsage,
+    OnceUsage,
+    FileReadFailed,
+    LargeFileConfirm,
+    FileCancelled,
+    ModelsFetched,
+    ModelsFetchFailed,
+    RateLimitWait,
+    Reconnecting,
+    ReasoningTruncated,
+    ToolCall,
+    ErrAuth,
+    ErrRateLimited,
+    ErrHttp,
+    ErrUnknownStatus,
+    ErrNetwork,
+    ErrTooManyRedirects,
+    ErrRedirect,
+    ErrParse,
+    ErrApi,
+    Interrupted,
+    EmptyResponse,
+    FallbackSwitch,
+    NoReasoning,
+    ContextWindowApplied,
+    PagerStatus,
+    SetUsage,
+    ModeSwitched,
+    ModeNotFound,
+    ProviderDefaults,
+    ValueUnset,
+    SourceDefault,
+    SourceEnv,
+    SourceCli,
+    SourceConfig,
+    SourceRuntime,
+    SourcePins,
+    #[cfg(feature = "keyring")]
+    KeySourceKeyring,
+    KeySourceFile,
+    KeySourceEnv,
+    CharCount,
+    ConfigProviders,
+    ApiKeySet,
+    ApiKeyMissing,
+    ModelCount,
+    SetInvalidValue,
+    SettingUpdated,
+    CompletionLabel,
+    CostEstimate,
+    BenchRun,
+    BenchFirstToken,
+    BenchElapsed,
+    BenchChars,
+    BenchCharsPerSec,
+    BenchTokensPerSec,
+    BenchAverage,
+    BenchProgress,
+    SessionCost,
+    BudgetUsage,
+    BudgetSet,
+    BudgetCleared,
+    BudgetCurrent,
+    BudgetNone,
+    BudgetExceeded,
+    BudgetConfirm,
+    LongInputConfirm,
+    SendCancelled,
+    ApiKeyNotFound,
+    KeyFileReadFailed,
+    ModelProviderNotFound,
+    ApiKeyInvalidHeader,
+    HookSpawnFailed,
+    HookInvalidUtf8,
+    HookNoOutput,
+    #[cfg(feature = "clipboard")]
+    ClipboardUnavailable,
+    #[cfg(feature = "clipboard")]
+    ClipboardReadFailed,
+    #[cfg(not(feature = "clipboard"))]
+    ClipboardDisabled,
+    CacheDirUnknown,
+    ProfileNotFound,
+    ConfigReadFailed,
+    ConfigParseFailed,
+    #[cfg(feature = "serve")]
+    ServeBindFailed,
+    ImportInvalidJson,
+    ImportIndexOutOfRange,
+    ImportNotChatGpt,
+    StdinReadFailed,
+    ChatHistoryEmpty,
+    PromptFileReadFailed,
+    SystemPromptFileReadFailed,
+    ModelListParseFailed,
+    DumpRedacted,
+}
+
+/// 查找当前语言下的界面文字
+pub fn t(key: MsgKey) -> &'static str {
+    let (zh, en) = match key {
+        MsgKey::Welcome => (
+            "欢迎使用Hambur，输入'exit'退出，'clear'清空聊天记录，直接输入模型关键字切换模型，连续按两次ESC退出程序",
+            "Welcome to Hambur. Type 'exit' to quit, 'clear' to clear the chat, a model keyword to switch models, or press ESC twice to exit",
+        ),
+        MsgKey::UserPrompt => ("你:", "You:"),
+        MsgKey::AssistantLabel => ("AI:", "AI:"),
+        MsgKey::Thinking => ("思考中", "Thinking"),
+        MsgKey::DoubleEscExit => ("[连续按两次ESC，程序已退出]", "[ESC pressed twice, exiting]"),
+        MsgKey::CtrlCExit => ("[按下Ctrl+C，程序已退出]", "[Ctrl+C pressed, exiting]"),
+        MsgKey::AutosaveDone => ("[聊天记录已自动保存到 {}]", "[Session saved to {}]"),
+        MsgKey::DumpRequestFailed => ("记录请求到 {} 失败: {}", "Failed to dump the request to {}: {}"),
+        MsgKey::AutosaveFailed => ("自动保存聊天记录到 {} 失败: {}", "Failed to save the session to {}: {}"),
+        MsgKey::ConfirmClear => ("确认清空聊天记录? [y/N] ", "Clear chat history? [y/N] "),
+        MsgKey::ImageClipUsage => ("用法: /image-clip <问题>", "Usage: /image-clip <question>"),
+        MsgKey::ClipboardNoImage => ("[剪贴板中没有图片]", "[There is no image in the clipboard]"),
+        MsgKey::ClipboardFailed => ("读取剪贴板中的图片失败: {}", "Failed to read the image from the clipboard: {}"),
+        MsgKey::ConfirmImport => ("导入的对话将替换当前的聊天记录，确认导入? [y/N] ", "The imported conversation replaces the current chat history, continue? [y/N] "),
+        MsgKey::ImportUsage => ("用法: /import chatgpt <文件> [#序号]，序号从1开始，例如 /import chatgpt conversations.json #2", "Usage: /import chatgpt <file> [#number], numbers start at 1, e.g. /import chatgpt conversations.json #2"),
+        MsgKey::ImportFailed => ("导入 {} 失败: {}", "Failed to import {}: {}"),
+        MsgKey::ImportEmpty => ("{} 中没有可以导入的消息", "{} has no messages to import"),
+        MsgKey::ImportSkipped => ("[跳过了 {} 条 system/tool 消息]", "[Skipped {} system/tool messages]"),
+        MsgKey::ImportDone => ("[已导入 {} 条消息: {}]", "[Imported {} messages: {}]"),
+        MsgKey::HistoryCleared => ("[聊天记录已清空]", "[Chat history cleared]"),
+        MsgKey::InputHistoryEmpty => ("[输入历史为空]", "[Input history is empty]"),
+        MsgKey::ConfirmInputHistoryClear => ("确认清空输入历史? [y/N] ", "Clear the input history? [y/N] "),
+        MsgKey::InputHistoryCleared => ("[输入历史已清空]", "[Input history cleared]"),
+        MsgKey::InputHistoryClearFailed => ("清空输入历史失败: {}", "Failed to clear the input history: {}"),
+        MsgKey::IdleCleared => ("[已超过 {} 分钟没有输入，所有标签页的聊天记录已自动清空]", "[No input for {} minutes, chat history of all tabs cleared]"),
+        MsgKey::RoleUsage => ("用法: /role <序号> <角色>，序号见 /history，例如 /role 3 system", "Usage: /role <index> <role>, indexes are shown by /history, e.g. /role 3 system"),
+        MsgKey::RoleIndexOutOfRange => ("序号 {} 超出范围，当前共有 {} 条消息", "Index {} is out of range, there are {} messages"),
+        MsgKey::RoleInvalid => ("无效的角色 {}，可用的角色: {}", "Invalid role {}, available roles: {}"),
+        MsgKey::RoleChanged => ("[第 {} 条消息的角色已改为 {}]", "[Message {} is now a {} message]"),
+        MsgKey::SaveUsage => ("用法: /save [路径] [--tag <标签>]...", "Usage: /save [path] [--tag <tag>]..."),
+        MsgKey::HistorySaved => ("[已将 {} 条消息保存到 {}]", "[Saved {} messages to {}]"),
+        MsgKey::SaveFailed => ("保存到 {} 失败: {}", "Failed to save to {}: {}"),
+        MsgKey::SessionIndexFailed => ("更新会话索引失败: {}", "Failed to update the session index: {}"),
+        MsgKey::ExportJsonlUsage => ("用法: /export-jsonl <路径>", "Usage: /export-jsonl <path>"),
+        MsgKey::ExportJsonlIncomplete => ("聊天记录中至少需要一条用户消息和一条回复才能导出", "The chat history needs at least one user message and one reply to export"),
+        MsgKey::ExportJsonlAppended => ("[已将 {} 条消息作为一行追加到 {}]", "[Appended {} messages as one line to {}]"),
+        MsgKey::SaveUserHeading => ("你", "You"),
+        MsgKey::SaveAssistantHeading => ("AI", "AI"),
+        MsgKey::SaveReasoningSummary => ("推理过程", "Reasoning"),
+        MsgKey::ComposeOn => ("[已进入编写模式: 回车换行，单独一行输入 /send 或按 Ctrl+D 发送，输入 /compose 退出]", "[Compose mode on: Enter inserts a newline, send with /send on its own line or Ctrl+D, /compose to leave]"),
+        MsgKey::ComposeOff => ("[已退出编写模式，回车直接发送]", "[Compose mode off, Enter sends]"),
+        MsgKey::HistoryEmpty => ("[暂无聊天记录]", "[No chat history yet]"),
+        MsgKey::SpeedSet => ("[输出速度已设置为每字符 {} 毫秒]", "[Output speed set to {} ms per character]"),
+        MsgKey::PinUsage => ("用法: /pin <内容>", "Usage: /pin <text>"),
+        MsgKey::UnpinUsage => ("用法: /unpin <序号>，序号来自 /pins 的列表", "Usage: /unpin <number>, using the numbers from /pins"),
+        MsgKey::PinAdded => ("[已固定，当前共有 {} 条固定消息]", "[Pinned, {} pinned messages in total]"),
+        MsgKey::PinRemoved => ("[已取消固定: {}]", "[Unpinned: {}]"),
+        MsgKey::PinIndexOutOfRange => ("序号 {} 超出范围，共有 {} 条固定消息", "Number {} is out of range, there are {} pinned messages"),
+        MsgKey::PinsEmpty => ("[没有固定的消息]", "[No pinned messages]"),
+        MsgKey::PinSaveFailed => ("保存固定消息失败: {}", "Failed to save pinned messages: {}"),
+        MsgKey::BufferOn => ("[已切换为接收完整后一次性显示]", "[Replies are now shown once fully received]"),
+        MsgKey::BufferOff => ("[已切换为流式输出]", "[Replies are now streamed]"),
+        MsgKey::Receiving => ("接收中", "Receiving"),
+        MsgKey::SpeedCurrent => ("[当前输出速度: 每字符 {} 毫秒]", "[Current output speed: {} ms per character]"),
+        MsgKey::SpeedUsage => ("用法: /speed <毫秒>，例如 /speed 0 关闭打字机效果", "Usage: /speed <ms>, e.g. /speed 0 disables the typewriter effect"),
+        MsgKey::Reloaded => ("[已重新加载 {} 个提供商，共 {} 个模型]", "[Reloaded {} providers with {} models]"),
+        MsgKey::CurrentModelMissing => ("警告: 当前模型 {} 已不在配置中，请切换到其他模型", "Warning: current model {} is no longer configured, please switch models"),
+        MsgKey::ReloadFailed => ("重新加载失败: {}", "Reload failed: {}"),
+        MsgKey::PrefillSet => ("[下一条回复将以 \"{}\" 开头]", "[The next reply will start with \"{}\"]"),
+        MsgKey::PrefillCleared => ("[已取消预填充]", "[Prefill cleared]"),
+        MsgKey::PrefillUnsupported => ("[提供商 {} 不支持预填充，已忽略]", "[Provider {} does not support prefill, ignored]"),
+        MsgKey::PipeSpawnFailed => ("启动外部命令 {} 失败: {}，回复将直接输出", "Failed to start command {}: {}, printing the reply directly"),
+        MsgKey::PipeWriteFailed => ("写入外部命令失败: {}", "Failed to write to the command: {}"),
+        MsgKey::PipeExitStatus => ("[外部命令异常退出: {}]", "[The command exited with {}]"),
+        MsgKey::TitleGenerating => ("[正在生成标题...]", "[Generating a title...]"),
+        MsgKey::TitleInstruction => ("用不超过十个字为以上对话起一个简短的标题，只输出标题本身，不要加引号或标点", "Give the conversation above a short title of at most six words. Reply with the title only, without quotes or punctuation"),
+        MsgKey::TitleFailed => ("[生成标题失败: {}，使用当前时间作为文件名]", "[Could not generate a title: {}, using the current time as the file name]"),
+        MsgKey::ReplayEmpty => ("还没有可以重放的回复", "There is no reply to replay yet"),
+        MsgKey::StreamFileFailed => ("无法写入文件 {}", "Cannot write to {}"),
+        MsgKey::StreamFileProgress => ("[已接收 {} 字符]", "[Received {} characters]"),
+        MsgKey::StreamFileDone => ("[已将 {} 字符写入 {}]", "[Wrote {} characters to {}]"),
+        MsgKey::StreamFileOneShot => ("--stream-to-file 只能在单次模式中使用（需要提供问题、--ask 或 --prompt-file）", "--stream-to-file only works in one-shot mode (give a prompt, --ask or --prompt-file)"),
+        MsgKey::HookFailed => ("[{} 钩子执行失败: {}，使用原始内容]", "[The {} hook failed: {}, using the original text]"),
+        MsgKey::HookProcessed => ("[post_receive 处理后的回复]", "[Reply after post_receive]"),
+        MsgKey::ReplyTruncated => ("[回复达到长度上限被截断，输入 /continue 继续生成]", "[The reply hit the length limit, type /continue to keep generating]"),
+        MsgKey::ContinueNothing => ("没有可以续写的回复", "There is no reply to continue"),
+        MsgKey::ContinueInstruction => ("请从上次中断的地方继续输出，不要重复已经输出的内容", "Continue exactly where you left off, without repeating what you already wrote"),
+        MsgKey::CompactNothing => ("最近一轮对话之前没有可以压缩的聊天记录", "There is nothing before the latest exchange to compact"),
+        MsgKey::CompactInstruction => ("请总结以上对话，保留后续对话需要的关键信息、结论和未解决的问题，只输出总结", "Summarize the conversation above, keeping the key facts, decisions and open questions needed to continue it. Output only the summary"),
+        MsgKey::CompactConfirm => ("确认用以上摘要代替之前的 {} 条消息? [y/N] ", "Replace the previous {} messages with this summary? [y/N] "),
+        MsgKey::CompactSummary => ("以下是之前对话的摘要：\n{}", "Summary of the earlier conversation:\n{}"),
+        MsgKey::CompactDone => ("[已将 {} 条消息压缩为摘要]", "[Compacted {} messages into a summary]"),
+        MsgKey::AutoCompacting => ("[上下文约 {} tokens，超过 {}，正在自动压缩较早的消息...]", "[Context is about {} tokens, over {}, compacting earlier messages...]"),
+        MsgKey::ModelSubstituted => ("[注意：请求的模型是 {}，但提供商返回的回复来自 {}]", "[Note: requested {}, but the provider answered with {}]"),
+        MsgKey::AutoCompactFailed => ("[自动压缩失败，照常发送: {}]", "[Auto-compaction failed, sending as usual: {}]"),
+        MsgKey::ModelSwitched => ("已切换到模型: {}", "Switched to model: {}"),
+        MsgKey::ModelOverride => ("[本轮使用模型: {}]", "[Using {} for this message]"),
+        MsgKey::ModelOverrideUsage => ("用法: @<模型关键字> <消息>", "Usage: @<model keyword> <message>"),
+        MsgKey::ModelOverrideNotFound => ("没有与 {} 匹配的模型", "No model matches {}"),
+        MsgKey::TabUsage => ("用法: /tab new [名称] | /tab list | /tab switch <序号>", "Usage: /tab new [name] | /tab list | /tab switch <number>"),
+        MsgKey::TabDefaultName => ("标签 {}", "Tab {}"),
+        MsgKey::TabCreated => ("[已新建并切换到标签页 {}: {}]", "[Opened and switched to tab {}: {}]"),
+        MsgKey::TabSwitched => ("[已切换到标签页 {}: {}，{} 条消息，模型 {}]", "[Switched to tab {}: {}, {} messages, model {}]"),
+        MsgKey::TabIndexOutOfRange => ("序号 {} 超出范围，共有 {} 个标签页", "Number {} is out of range, there are {} tabs"),
+        MsgKey::TabListEntry => ("{} 条消息，模型 {}", "{} messages, model {}"),
+        MsgKey::ModelIndexUsage => ("用法: /model <序号>，序号来自最近一次 /models 的列表", "Usage: /model <number>, using the numbers from the last /models listing"),
+        MsgKey::ModelIndexOutOfRange => ("序号 {} 超出范围，最近一次列表共有 {} 个模型", "Number {} is out of range, the last listing has {} models"),
+        MsgKey::ModelListEmpty => ("没有匹配的模型", "No matching models"),
+        MsgKey::NoModelListing => ("请先输入 /models 列出模型", "Run /models first to list the models"),
+        MsgKey::FavoriteAdded => ("[已收藏模型 {}]", "[Added {} to favorites]"),
+        MsgKey::FavoriteRemoved => ("[已取消收藏模型 {}]", "[Removed {} from favorites]"),
+        MsgKey::FavoriteSaveFailed => ("保存收藏状态失败: {}", "Failed to save favorites: {}"),
+        #[cfg(feature = "serve")]
+        MsgKey::ServeListening => ("HTTP服务已启动: http://{}/", "HTTP server listening on http://{}/"),
+        #[cfg(feature = "serve")]
+        MsgKey::ServeRequest => ("[{}] 转发 {} 条消息", "[{}] forwarding {} messages"),
+        #[cfg(feature = "serve")]
+        MsgKey::ServeForbidden => ("只接受来自本服务页面的请求", "Only requests from this server's own page are accepted"),
+        MsgKey::ProviderNotFound => ("未找到提供商 {}，输入 /provider 查看所有提供商", "Provider {} not found, type /provider to list providers"),
+        MsgKey::ProviderNoModels => ("提供商 {} 没有可用的模型", "Provider {} has no models"),
+        MsgKey::SetHeaderUsage => ("用法: /set-header <名称> [值]，不带值时删除该请求头", "Usage: /set-header <name> [value], omit the value to remove the header"),
+        MsgKey::HeaderSet => ("[已为本次会话设置请求头 {}]", "[Set header {} for this session]"),
+        MsgKey::HeaderRemoved => ("[已删除请求头 {}]", "[Removed header {}]"),
+        MsgKey::HeaderInvalid => ("无效的请求头: {}: {}", "Invalid header: {}: {}"),
+        MsgKey::WaitingForReady => ("[等待本地服务 {}...]", "[Waiting for {} to start...]"),
+        MsgKey::ReadyTimeout => ("等待 {} 就绪超时（{} 秒），继续启动", "Timed out after waiting for {} ({}s), continuing"),
+        MsgKey::ModelInfoTitle => ("[当前模型]", "[Current model]"),
+        MsgKey::InfoId => ("ID:      ", "ID:       "),
+        MsgKey::InfoName => ("名称:    ", "Name:     "),
+        MsgKey::InfoProvider => ("提供商:  ", "Provider: "),
+        MsgKey::InfoApiBase => ("API地址: ", "API base: "),
+        MsgKey::InfoKeyEnv => ("密钥变量:", "Key env:  "),
+        MsgKey::KeySet => ("已设置", "set"),
+        MsgKey::KeyUnset => ("未设置", "not set"),
+        MsgKey::MultipleModels => ("找到多个匹配的模型，请使用上下方向键选择，输入文字继续筛选:", "Multiple models match, use the arrow keys to choose or type to filter:"),
+        MsgKey::MenuFilter => ("筛选: {}", "Filter: {}"),
+        MsgKey::MenuNoMatch => ("没有匹配的模型，按退格放宽筛选", "No models match, press Backspace to widen the filter"),
+        MsgKey::ModelSwitchCancelled => ("已取消模型切换", "Model switch cancelled"),
+        MsgKey::OnceUsage => ("用法: /once <临时指令> | <消息>，例如 /once 只用一个词回答 | Rust 好学吗", "Usage: /once <instruction> | <message>, e.g. /once answer in one word | is Rust easy to learn"),
+        MsgKey::FileUsage => ("用法: /file <路径> [问题]", "Usage: /file <path> [question]"),
+        MsgKey::FileReadFailed => ("无法读取文件 {}: {}", "Cannot read file {}: {}"),
+        MsgKey::LargeFileConfirm => ("文件 {} 较大（{} KB），确认发送? [y/N] ", "File {} is large ({} KB), send anyway? [y/N] "),
+        MsgKey::FileCancelled => ("已取消发送文件", "File not sent"),
+        MsgKey::ModelsFetched => ("[已从 {} 获取 {} 个模型]", "[{}: fetched {} models]"),
+        MsgKey::ModelsFetchFailed => ("获取 {} 的模型列表失败: {}", "Failed to fetch models from {}: {}"),
+        MsgKey::RateLimitWait => ("[限流等待 {} 秒...]", "[Rate limited, waiting {} s...]"),
+        MsgKey::Reconnecting => ("[连接中断: {}，正在重连 ({}/{})...]", "[Connection lost: {}, reconnecting ({}/{})...]"),
+        MsgKey::ReasoningTruncated => ("[推理过程已截断]", "[reasoning truncated]"),
+        MsgKey::ToolCall => ("[工具调用] {}({})", "[tool call] {}({})"),
+        MsgKey::ErrAuth => ("认证失败(401): API密钥可能无效或已过期。请检查{}环境变量设置。\n原始数据: {}", "Authentication failed (401): the API key may be invalid or expired. Check the {} environment variable.\nRaw data: {}"),
+        MsgKey::ErrRateLimited => ("请求过多(429): 已超出API速率限制。\n原始数据: {}", "Too many requests (429): API rate limit exceeded.\nRaw data: {}"),
+        MsgKey::ErrHttp => ("API请求失败({}): {}\n原始数据: {}", "API request failed ({}): {}\nRaw data: {}"),
+        MsgKey::ErrUnknownStatus => ("未知错误", "Unknown error"),
+        MsgKey::ErrNetwork => ("API请求失败: {}\n请检查网络连接和API端点配置", "API request failed: {}\nCheck your network connection and API endpoint configuration"),
+        MsgKey::ErrTooManyRedirects => ("API请求失败: 重定向次数过多或出现循环，最后的地址: {}\n请检查提供商的 api_base 配置", "API request failed: too many redirects or a redirect loop, last URL: {}\nCheck the provider's api_base"),
+        MsgKey::ErrRedirect => ("API请求失败: 接口返回重定向({})，目标地址: {}\n已关闭自动跟随重定向，请更新 api_base 或设置 HAMBUR_MAX_REDIRECTS", "API request failed: the endpoint redirected ({}) to {}\nRedirects are not followed, update api_base or set HAMBUR_MAX_REDIRECTS"),
+        MsgKey::ErrParse => ("解析响应失败: {}\n原始数据: {}", "Failed to parse response: {}\nRaw data: {}"),
+        MsgKey::ErrApi => ("API返回错误: {}", "API returned an error: {}"),
+        MsgKey::Interrupted => ("[已中断输出]", "[Output interrupted]"),
+        MsgKey::ContextWindowApplied => ("[上下文: 发送最近 {} 条消息，共 {} 条]", "[Context: sending the last {} of {} messages]"),
+        MsgKey::EmptyResponse => ("[模型未返回内容]", "[The model returned no content]"),
+        MsgKey::FallbackSwitch => ("[切换到备用: {}]", "[Falling back to {}]"),
+        MsgKey::NoReasoning => ("[模型没有返回推理内容，正文未显示]", "[The model returned no reasoning, the answer was not shown]"),
+        MsgKey::ModeSwitched => ("[已切换到 {} 模式: {}]", "[Switched to {} mode: {}]"),
+        MsgKey::ModeNotFound => ("未找到模式 {}，输入 /mode 查看所有模式", "Mode {} not found, type /mode to list modes"),
+        MsgKey::ProviderDefaults => ("使用提供商的默认参数", "provider defaults"),
+        MsgKey::SetUsage => ("用法: /set <名称> <值>，可用的名称: n（回复数量）、seed（随机种子，off 取消）", "Usage: /set <name> <value>, available names: n (number of completions), seed (random seed, off to unset)"),
+        MsgKey::ValueUnset => ("未设置", "unset"),
+        MsgKey::SourceDefault => ("默认值", "default"),
+        MsgKey::SourceEnv => ("环境变量", "env"),
+        MsgKey::SourceCli => ("命令行参数", "cli"),
+        MsgKey::SourceConfig => ("配置文件", "config"),
+        MsgKey::SourceRuntime => ("运行时修改", "runtime"),
+        MsgKey::SourcePins => ("固定消息文件", "pins file"),
+        #[cfg(feature = "keyring")]
+        MsgKey::KeySourceKeyring => ("系统钥匙串", "keychain"),
+        MsgKey::KeySourceFile => ("文件 {}", "file {}"),
+        MsgKey::KeySourceEnv => ("环境变量 {}", "env {}"),
+        MsgKey::CharCount => ("{} 个字符", "{} chars"),
+        MsgKey::ConfigProviders => ("已启用的提供商:", "Enabled providers:"),
+        MsgKey::ApiKeySet => ("已设置", "set"),
+        MsgKey::ApiKeyMissing => ("未设置", "missing"),
+        MsgKey::ModelCount => ("{} 个模型", "{} models"),
+        MsgKey::SetInvalidValue => ("无效的设置值: {} {}", "Invalid value for setting: {} {}"),
+        MsgKey::SettingUpdated => ("已设置 {} = {}", "Set {} = {}"),
+        MsgKey::CostEstimate => ("[≈ ${}]", "[≈ ${}]"),
+        MsgKey::BenchRun => ("轮次", "Run"),
+        MsgKey::BenchFirstToken => ("首字延迟", "First token"),
+        MsgKey::BenchElapsed => ("总耗时", "Total"),
+        MsgKey::BenchChars => ("字符数", "Chars"),
+        MsgKey::BenchCharsPerSec => ("字符/秒", "Chars/s"),
+        MsgKey::BenchTokensPerSec => ("token/秒", "Tokens/s"),
+        MsgKey::BenchAverage => ("平均", "Average"),
+        MsgKey::BenchProgress => ("[测速 {}/{}]", "[Benchmark {}/{}]"),
+        MsgKey::SessionCost => ("[本次会话估算费用 ≈ ${}]", "[Estimated session cost ≈ ${}]"),
+        MsgKey::BudgetUsage => ("用法: /budget <美元>，例如 /budget 0.50，/budget 0 取消上限", "Usage: /budget <USD>, e.g. /budget 0.50, /budget 0 removes the cap"),
+        MsgKey::BudgetSet => ("[会话费用上限已设置为 ${}]", "[Session spend cap set to ${}]"),
+        MsgKey::BudgetCleared => ("[已取消会话费用上限]", "[Session spend cap removed]"),
+        MsgKey::BudgetCurrent => ("[本次会话估算费用 ≈ ${}，上限 ${}]", "[Estimated session cost ≈ ${}, cap ${}]"),
+        MsgKey::BudgetNone => ("[本次会话估算费用 ≈ ${}，未设置上限]", "[Estimated session cost ≈ ${}, no cap set]"),
+        MsgKey::BudgetExceeded => ("本次会话估算费用 ${} 已达到上限 ${}", "Estimated session cost ${} has reached the cap of ${}"),
+        MsgKey::BudgetConfirm => ("仍然发送? [y/N] ", "Send anyway? [y/N] "),
+        MsgKey::LongInputConfirm => ("这条消息共 {} 个字符，超过了 {} 字符的提醒阈值，确认发送? [y/N] ", "This message has {} characters, more than the {} character warning threshold. Send it? [y/N] "),
+        MsgKey::SendCancelled => ("[已取消发送]", "[Message not sent]"),
+        MsgKey::CompletionLabel => ("[回复 {}/{}]", "[Completion {}/{}]"),
+        MsgKey::PagerStatus => (" 第 {}-{} 行，共 {} 行  ↑↓/PgUp/PgDn 滚动，q 退出 ", " Lines {}-{} of {}  ↑↓/PgUp/PgDn to scroll, q to quit "),
+        MsgKey::ModelProviderNotFound => ("未找到模型 {} 的提供商", "No provider found for model {}"),
+        MsgKey::ApiKeyInvalidHeader => ("提供商 {} 的API密钥包含无法放入请求头的字符", "The API key of provider {} contains characters that cannot be sent in a header"),
+        MsgKey::HookSpawnFailed => ("无法启动命令", "Failed to start the command"),
+        MsgKey::HookInvalidUtf8 => ("输出不是有效的UTF-8", "The output is not valid UTF-8"),
+        MsgKey::HookNoOutput => ("没有输出", "No output"),
+        #[cfg(feature = "clipboard")]
+        MsgKey::ClipboardUnavailable => ("无法访问剪贴板", "Cannot access the clipboard"),
+        #[cfg(feature = "clipboard")]
+        MsgKey::ClipboardReadFailed => ("读取剪贴板失败", "Failed to read the clipboard"),
+        #[cfg(not(feature = "clipboard"))]
+        MsgKey::ClipboardDisabled => ("编译时未启用 clipboard 特性，请使用 `cargo build --features clipboard` 重新编译", "Built without the clipboard feature, rebuild with `cargo build --features clipboard`"),
+        MsgKey::CacheDirUnknown => ("无法确定缓存目录", "Cannot determine the cache directory"),
+        MsgKey::ProfileNotFound => ("未找到配置档 {}", "Profile {} not found"),
+        MsgKey::ConfigReadFailed => ("读取配置文件 {} 失败", "Failed to read the config file {}"),
+        MsgKey::ConfigParseFailed => ("解析配置文件 {} 失败", "Failed to parse the config file {}"),
+        #[cfg(feature = "serve")]
+        MsgKey::ServeBindFailed => ("无法监听地址 {}", "Cannot listen on {}"),
+        MsgKey::ImportInvalidJson => ("不是有效的JSON", "Not valid JSON"),
+        MsgKey::ImportIndexOutOfRange => ("序号 {} 超出范围，文件中共有 {} 个对话", "Number {} is out of range, the file has {} conversations"),
+        MsgKey::ImportNotChatGpt => ("不是ChatGPT导出的对话格式", "Not a conversation exported from ChatGPT"),
+        MsgKey::StdinReadFailed => ("读取stdin失败", "Failed to read stdin"),
+        MsgKey::ChatHistoryEmpty => ("聊天记录为空", "The chat history is empty"),
+        MsgKey::PromptFileReadFailed => ("读取问题文件 {} 失败", "Failed to read the prompt file {}"),
+        MsgKey::SystemPromptFileReadFailed => ("读取系统提示词文件 {} 失败", "Failed to read the system prompt file {}"),
+        MsgKey::ModelListParseFailed => ("解析模型列表失败", "Failed to parse the model list"),
+        MsgKey::DumpRedacted => ("<已隐藏>", "<redacted>"),
+        MsgKey::ApiKeyNotFound => ("未找到提供商 {} 的API密钥，请设置{}环境变量", "No API key found for provider {}, please set the {} environment variable"),
+        MsgKey::KeyFileReadFailed => ("读取密钥文件 {} 失败", "Failed to read the key file {}"),
+    };
+    if *LANG == Lang::En { en } else { zh }
+}
+
+/// 查找界面文字并依次替换其中的 `{}` 占位符
+/// 只扫描一遍模板，参数中本身带有的 `{}`（例如模型回复或路径）不会被后面的参数替换；参数不足时保留占位符
+pub fn tf(key: MsgKey, args: &[&dyn Display]) -> String {
+    let mut pieces = t(key).split("{}");
+    let mut text = pieces.next().unwrap_or_default().to_string();
+    let mut args = args.iter();
+    for piece in pieces {
+        match args.next() {
+            Some(arg) => text.push_str(&arg.to_string()),
+            None => text.push_str("{}"),
+        }
+        text.push_str(piece);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_placeholders_in_order() {
+        let text = tf(MsgKey::TabCreated, &[&2, &"work"]);
+        assert!(text.contains("2: work"), "{}", text);
+    }
+
+    #[test]
+    fn placeholders_in_arguments_are_kept() {
+        let text = tf(MsgKey::TabCreated, &[&"{}", &"work"]);
+        assert!(text.contains("{}: work"), "{}", text);
+    }
+
+    #[test]
+    fn missing_arguments_keep_placeholders() {
+        let text = tf(MsgKey::TabCreated, &[&2]);
+        assert!(text.contains("2: {}"), "{}", text);
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{Context, Result, bail};
+use chrono::{Local, TimeZone};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::i18n::{MsgKey, t, tf};
+use crate::models::ChatMessage;
+
+/// ChatGPT 导出的一个对话，`conversations.json` 是这种对象的数组
+#[derive(Debug, Deserialize)]
+struct Conversation {
+    #[serde(default)]
+    title: Option<String>,
+    mapping: HashMap<String, Node>,
+    /// 最后显示的消息，从这里沿 `parent` 回溯得到当前分支
+    #[serde(default)]
+    current_node: Option<String>,
+}
+
+/// 消息树中的一个节点，重新生成的回复和编辑过的问题会形成多个子节点
+#[derive(Debug, Deserialize)]
+struct Node {
+    #[serde(default)]
+    message: Option<NodeMessage>,
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NodeMessage {
+    author: Author,
+    #[serde(default)]
+    content: Option<NodeContent>,
+    #[serde(default)]
+    create_time: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Author {
+    role: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NodeContent {
+    /// 文本消息的 `parts` 是字符串，图片等附件是对象，只保留字符串
+    #[serde(default)]
+    parts: Vec<serde_json::Value>,
+}
+
+/// 导入的对话
+#[derive(Debug)]
+pub struct Imported {
+    pub title: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    /// 跳过的非空 system 和 tool 消息数
+    pub skipped: usize,
+}
+
+/// 解析 ChatGPT 导出的 JSON，可以是单个对话，也可以是包含多个对话的 `conversations.json`，
+/// `index` 为对话在数组中的序号（从1开始）
+/// 只保留当前分支上的 user 和 assistant 文本消息
+pub fn from_chatgpt(text: &str, index: usize) -> Result<Imported> {
+    let value: serde_json::Value = serde_json::from_str(text).context(t(MsgKey::ImportInvalidJson))?;
+    let conversation = match value {
+        serde_json::Value::Array(mut conversations) => {
+            if index == 0 || index > conversations.len() {
+                bail!(tf(MsgKey::ImportIndexOutOfRange, &[&index, &conversations.len()]));
+            }
+            conversations.swap_remove(index - 1)
+        },
+        conversation => conversation,
+    };
+    let conversation: Conversation = serde_json::from_value(conversation).context(t(MsgKey::ImportNotChatGpt))?;
+
+    let mut messages = Vec::new();
+    let mut skipped = 0;
+    for id in main_branch(&conversation) {
+        let Some(message) = conversation.mapping.get(&id).and_then(|node| node.message.as_ref()) else {
+            continue;
+        };
+        let content = message.content.iter()
+            .flat_map(|content| &content.parts)
+            .filter_map(|part| part.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if content.trim().is_empty() {
+            continue;
+        }
+        match message.author.role.as_str() {
+            role @ ("user" | "assistant") => {
+                let mut imported = ChatMessage::new(role, content);
+                imported.timestamp = message.create_time
+                    .and_then(|time| Local.timestamp_millis_opt((time * 1000.0) as i64).single());
+                messages.push(imported);
+            },
+            _ => skipped += 1,
+        }
+    }
+
+    Ok(Imported { title: conversation.title, messages, skipped })
+}
+
+/// 当前分支上从根节点到最后一条消息的节点ID
+/// 没有 `current_node` 时从根节点开始，每次取最后一个子节点（最近一次重新生成的回复）
+fn main_branch(conversation: &Conversation) -> Vec<String> {
+    let mapping = &conversation.mapping;
+    let mut branch = Vec::new();
+    if let Some(current) = conversation.current_node.as_ref().filter(|id| mapping.contains_key(*id)) {
+        let mut id = Some(current.clone());
+        // 节点数作为上限，防止损坏的文件中出现循环
+        while let Some(node_id) = id.take() && branch.len() <= mapping.len() {
+            id = mapping.get(&node_id).and_then(|node| node.parent.clone());
+            branch.push(node_id);
+        }
+        branch.reverse();
+        return branch;
+    }
+
+    let mut id = mapping.iter()
+        .find(|(_, node)| node.parent.as_ref().is_none_or(|parent| !mapping.contains_key(parent)))
+        .map(|(id, _)| id.clone());
+    while let Some(node_id) = id.take() && branch.len() <= mapping.len() {
+        id = mapping.get(&node_id).and_then(|node| node.children.last().cloned());
+        branch.push(node_id);
+    }
+    branch
+}
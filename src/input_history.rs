@@ -0,0 +1,75 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// 最多保留的输入历史条数
+const MAX_ENTRIES: usize = 1000;
+
+/// 交互模式下的输入历史，可通过上下方向键找回之前的输入
+/// 每条记录以JSON字符串的形式占一行，保存在缓存目录中，编写模式的多行输入也能完整恢复
+#[derive(Debug, Default)]
+pub struct InputHistory {
+    entries: Vec<String>,
+    /// 为 None 时不读写文件，也不记录新的输入
+    path: Option<PathBuf>,
+}
+
+impl InputHistory {
+    /// 读取保存的输入历史，`enabled` 为 false 时返回不记录任何内容的空历史
+    pub fn load(enabled: bool) -> Self {
+        let Some(path) = enabled.then(history_path).flatten() else {
+            return Self::default();
+        };
+        let mut entries: Vec<String> = std::fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+        Self { entries, path: Some(path) }
+    }
+
+    /// 记录一条输入，空白输入和与上一条相同的输入不重复记录
+    pub fn push(&mut self, entry: &str) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if entry.trim().is_empty() || self.entries.last().is_some_and(|last| last == entry) {
+            return;
+        }
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        // 写入失败只影响下次启动时的历史，不打断当前的输入
+        let _ = append_entry(path, entry);
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// 清空内存中的历史并删除保存的文件
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.entries.clear();
+        match &self.path {
+            Some(path) if path.exists() => std::fs::remove_file(path),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("input_history"))
+}
+
+fn append_entry(path: &Path, entry: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+}
@@ -1,93 +1,261 @@
 use anyhow::{Context, Result};
 use dotenv::dotenv;
 use futures::StreamExt;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
-use std::env;
-use std::io::{self, Write};
-use crossterm::{event::{poll, read, Event, KeyCode, KeyEvent}, 
-                style::{Stylize, Color, SetForegroundColor, Print, ResetColor},
-                terminal::{Clear, ClearType},
-                cursor::{MoveUp, MoveToColumn},
+use reqwest::header::HeaderMap;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::Path;
+use crossterm::{event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers}, 
+                style::{Color, Print, StyledContent},
+                terminal::{size, Clear, ClearType},
+                cursor::{position, MoveTo, MoveToColumn, MoveUp},
                 execute, queue};
 use std::time::Duration;
+use tokio::io::AsyncBufReadExt;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
+mod bench;
+mod cli;
+mod clipboard;
+mod color;
+mod config;
+mod cost;
+mod dump;
+mod error;
+mod export;
+mod headers;
+mod hooks;
+mod i18n;
+mod import;
+mod input_history;
+mod markdown;
+mod commands;
 mod models;
+mod pager;
+mod paths;
+mod pins;
+mod pipe;
+mod ratelimit;
+mod reply;
+mod secrets;
+mod sessions;
+#[cfg(feature = "serve")]
+mod serve;
+mod settings;
+mod sse;
+mod strip;
+mod stream_file;
+mod tabs;
 mod terminal;
-use models::{ChatMessage, ChatRequest, ChatResponse, find_models, get_provider_by_model};
-use terminal::RawModeGuard;
+use clap::{CommandFactory, Parser};
+use bench::StreamStats;
+use cli::Cli;
+use color::{Paint, Reset, SetColor};
+use commands::{Command, parse_command};
+use config::{Config, SamplingPreset};
+use error::ChatError;
+use i18n::{MsgKey, t, tf};
+use input_history::InputHistory;
+use markdown::{MarkdownRenderer, Segment};
+use pipe::OutputPipe;
+use reply::{Part, ReplyParts};
+use models::{ChatMessage, ChatRequest, Model, ModelProvider, StreamOptions, ToolCall, MESSAGE_ROLES, accumulate_tool_calls, estimate_tokens, fetch_models, is_same_model, find_models, get_provider_by_model, load_providers, read_last_model, toggle_favorite, write_last_model, write_models_cache, DEFAULT_MODEL};
+use secrets::resolve_api_key;
+use settings::{DEFAULT_MODE, HttpVersion, Settings, Source};
+use strip::ContentFilter;
+use sse::{Frame, FrameDecoder};
+use stream_file::FileSink;
+use tabs::Tabs;
+use terminal::{ColorResetGuard, FullScreenGuard, RawModeGuard, ThinkingIndicator, confirm};
 
-async fn send_chat_request(client: &reqwest::Client, message: &str, model_id: &str, message_history: &mut Vec<ChatMessage>) -> Result<String> {
+/// 只对单轮请求生效的选项
+#[derive(Debug, Default, Clone, Copy)]
+struct TurnOptions<'a> {
+    /// 预填充的assistant回复开头
+    prefill: Option<&'a str>,
+    /// 只用于本轮请求的临时系统消息，不保存到聊天记录
+    ephemeral_system: Option<&'a str>,
+    /// 测速模式下记录本轮的耗时统计，同时要求接口返回用量
+    stats: Option<&'a Cell<StreamStats>>,
+    /// 续写上一条回复：预填充的内容已经显示过，不再重复输出
+    continuation: bool,
+    /// 随本轮问题发送的图片（data URL）
+    images: &'a [String],
+}
+
+/// 收到 `finish_reason` 后继续等待用量统计等后续数据的时间
+const FINISH_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// 思考提示动画每一帧的间隔
+const THINKING_FRAME_INTERVAL: Duration = Duration::from_millis(400);
+
+async fn send_chat_request(client: &reqwest::Client, providers: &[ModelProvider], message: &str, model_id: &str, message_history: &mut Vec<ChatMessage>, settings: &Settings, options: TurnOptions<'_>) -> Result<String> {
     let start_time = tokio::time::Instant::now();
-    let provider = get_provider_by_model(model_id)
-        .context(format!("未找到模型 {} 的提供商", model_id))?;
+    let provider = get_provider_by_model(providers, model_id)
+        .context(tf(MsgKey::ModelProviderNotFound, &[&model_id]))?;
     
-    let api_key = env::var(&provider.api_key_env)
-        .context(format!("未找到{}环境变量", provider.api_key_env))?;
+    let api_key = resolve_api_key(provider)?;
+    let mut headers = headers::build_headers(&api_key, provider)?;
+    headers::insert_extra(&mut headers, &settings.extra_headers)?;
 
-    let mut headers = HeaderMap::new();
-    
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", api_key))?,
-    );
-    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    let mut user_message = ChatMessage::new("user", message);
+    user_message.images = options.images.to_vec();
+    message_history.push(user_message);
 
-    message_history.push(ChatMessage {
-        role: "user".to_string(),
-        content: message.to_string(),
-    });
+    // 配置了价格的模型要求返回用量统计，用于估算费用
+    let pricing = provider.models.iter()
+        .find(|m| m.id == model_id)
+        .and_then(|m| m.pricing);
+
+    // 只发送最近的若干条消息，完整的聊天记录仍保存在本地
+    let context_start = match settings.context_window {
+        Some(window) if window < message_history.len() => {
+            eprintln!("{}", tf(MsgKey::ContextWindowApplied, &[&window, &message_history.len()]).dim());
+            message_history.len() - window
+        },
+        _ => 0,
+    };
 
-    let request = ChatRequest {
+    let mut request = ChatRequest {
         model: model_id.to_string(),
-        messages: message_history.clone(),
+        messages: message_history[context_start..].to_vec(),
         stream: true,
+        n: (settings.completions > 1).then_some(settings.completions),
+        seed: settings.seed,
+        temperature: settings.sampling().temperature,
+        top_p: settings.sampling().top_p,
+        stream_options: (pricing.is_some() || options.stats.is_some()).then_some(StreamOptions { include_usage: true }),
+    };
+    // 系统提示词和固定的消息只附加在请求中，不保存到聊天记录
+    let mut preamble = settings.preamble();
+    if let Some(instruction) = options.ephemeral_system {
+        preamble.push(ChatMessage::new("system", instruction));
+    }
+    request.messages.splice(0..0, preamble);
+    
+    // 预填充：在请求末尾附加一条不完整的assistant消息，模型会从这段文本继续生成
+    let prefill = match options.prefill {
+        Some(prefill) if provider.supports_prefill => Some(prefill),
+        Some(_) => {
+            eprintln!("{}", tf(MsgKey::PrefillUnsupported, &[&provider.name]).yellow());
+            None
+        },
+        None => None,
     };
+    if let Some(prefill) = prefill {
+        request.messages.push(ChatMessage::new("assistant", prefill));
+    }
+
+    let body = request.to_body(provider)?;
+    if let Some(path) = &settings.dump_requests
+        && let Err(e) = dump::append_request(path, &provider.api_base, &headers, &body) {
+        eprintln!("{}", tf(MsgKey::DumpRequestFailed, &[&path.display(), &e]).red());
+    }
 
     // 发送请求
     // 尝试发送请求，如果失败则直接输出错误信息
-    if env::var("HAMBUR_DEBUG").is_ok() {
+    if settings.debug {
+        eprintln!("[DEBUG] 使用提供商: {}", provider.name);
         eprintln!("[DEBUG] 请求准备耗时: {:?}", start_time.elapsed());
     }
 
+    // 客户端限流：超过提供商配置的每分钟请求数时先等待
+    if let Some(rpm) = provider.rate_limit_rpm {
+        let wait = ratelimit::LIMITER.reserve(&provider.name, rpm);
+        if !wait.is_zero() {
+            eprintln!("{}", tf(MsgKey::RateLimitWait, &[&format!("{:.1}", wait.as_secs_f64())]).dim());
+            tokio::time::sleep(wait).await;
+        }
+    }
+
     let request_start_time = tokio::time::Instant::now();
     let response = match client
-        .post(&provider.api_base)
-        .headers(headers)
-        .json(&request)
+        .post(provider.request_url(model_id))
+        .headers(headers.clone())
+        .json(&body)
         .send()
         .await {
             Ok(resp) => {
                 // 检查HTTP状态码
                 if resp.status().is_success() {
                     resp.bytes_stream()
+                } else if resp.status().is_redirection() {
+                    // 关闭了自动跟随重定向
+                    let status = resp.status().as_u16();
+                    let location = resp.headers()
+                        .get(reqwest::header::LOCATION)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string();
+                    message_history.pop();
+                    return Err(ChatError::Redirect { status, location }.into());
                 } else {
-                    let status = resp.status();
-                    let error_text = resp.text().await?;
-                    
-                    let error_msg = match status.as_u16() {
-                        401 => format!("认证失败(401): API密钥可能无效或已过期。请检查{}环境变量设置。\n原始数据: {}", provider.api_key_env, error_text),
-                        429 => format!("请求过多(429): 已超出API速率限制。\n原始数据: {}", error_text),
-                        _ => format!("API请求失败({}): {}\n原始数据: {}", status.as_u16(), status.canonical_reason().unwrap_or("未知错误"), error_text)
-                    };
-                    
-                    print!("{}", error_msg.clone().red());
-                    io::stdout().flush()?;
-                    return Ok(error_msg);
+                    let status = resp.status().as_u16();
+                    let error_text = resp.text().await.unwrap_or_default();
+                    // 本轮对话失败，移除刚加入的用户消息，保持历史记录中的消息成对出现
+                    message_history.pop();
+                    return Err(ChatError::from_status(status, error_text, &provider.api_key_env).into());
                 }
             },
             Err(e) => {
-                let error_msg = format!("API请求失败: {}\n请检查网络连接和API端点配置", e);
-                print!("{}", error_msg.clone().red());
-                io::stdout().flush()?;
-                return Ok(error_msg);
+                message_history.pop();
+                return Err(ChatError::from_request_error(e).into());
             }
         };
 
-    let mut full_response = String::new();
+    if settings.completions > 1 {
+        eprintln!("{}", tf(MsgKey::CompletionLabel, &[&1, &settings.completions]).dim());
+    }
+
+    // 去掉模型在回复开头多余输出的前缀，预填充和续写时回复的开头是已有的文本，不做处理；
+    // 回复中出现停止标记时在客户端结束本轮回复，标记及之后的内容不显示也不保存
+    let mut filter = ContentFilter::new(
+        settings.strip_prefix.as_deref().filter(|_| prefill.is_none()),
+        settings.client_stop.as_deref(),
+    );
+
+    // 指定了 `--pipe` 时正文写入外部命令，启动失败时仍输出到stdout
+    let mut pipe = match &settings.pipe_command {
+        Some(command) => match OutputPipe::spawn(command) {
+            Ok(pipe) => Some(pipe),
+            Err(e) => {
+                eprintln!("{}", tf(MsgKey::PipeSpawnFailed, &[command, &e]).red());
+                None
+            },
+        },
+        None => None,
+    };
+
+    // 指定了 `--stream-to-file` 时正文追加到文件，不累积到 `reply` 中
+    let mut file_sink = match settings.stream_to_file.as_deref().map(FileSink::open).transpose() {
+        Ok(sink) => sink,
+        Err(e) => {
+            message_history.pop();
+            let path = settings.stream_to_file.as_deref().unwrap_or_else(|| Path::new(""));
+            return Err(anyhow::Error::from(e).context(tf(MsgKey::StreamFileFailed, &[&path.display()])));
+        },
+    };
+
+    // 流式输出的内容是预填充文本的延续，保存到历史记录的是完整的回复
+    let mut reply = ReplyParts::default();
+    // 光标是否位于行首，用于在回复结束后正确换行
+    let mut at_line_start = true;
+    if let Some(prefill) = prefill {
+        reply.push_content(prefill);
+        if let Some(output) = pipe.as_mut() {
+            if !options.continuation && output.write(prefill).is_err() {
+                pipe = None;
+            }
+        } else if !options.continuation {
+            print!("{}", if io::stdout().is_terminal() { prefill.green().to_string() } else { prefill.to_string() });
+            at_line_start = prefill.ends_with('\n');
+        }
+    }
     
     io::stdout().flush()?;
 
-    if env::var("HAMBUR_DEBUG").is_ok() {
+    if settings.debug {
         eprintln!("[DEBUG] 请求发送耗时: {:?}", request_start_time.elapsed());
     }
 
@@ -96,406 +264,2649 @@ async fn send_chat_request(client: &reqwest::Client, message: &str, model_id: &s
     let mut total_chunks = 0;
     let mut total_chars = 0;
     let mut total_delay = tokio::time::Duration::from_secs(0);
+    let mut reconnects = 0;
+    let mut tool_calls: Vec<ToolCall> = Vec::new();
+    let mut reasoning_chars = 0;
+    let mut interrupted = false;
+    let mut reasoning_truncated = false;
+    // 序号不为0的回复内容，按序号排列
+    let mut extra_completions: BTreeMap<u32, String> = BTreeMap::new();
+    let mut usage = None;
+    // 响应中的ID和实际使用的模型，取第一次出现的值，重连后也不更新
+    let mut response_id: Option<String> = None;
+    let mut response_model: Option<String> = None;
+    // 收到第一个字符的时间，用于测速
+    let mut first_token = None;
+    // 第一个回复结束的原因
+    let mut finish_reason: Option<String> = None;
+
+    // 回答内容输出到stdout，被重定向到文件或管道时不输出颜色控制符
+    let styled = io::stdout().is_terminal();
+    // 只在终端中渲染Markdown，重定向时保留原文
+    let mut markdown = (settings.markdown && styled).then(MarkdownRenderer::new);
+
+    // 启用原始模式以捕获键盘事件，标准输入不是终端时（如脚本调用）无法捕获按键
+    let raw_guard = if io::stdin().is_terminal() {
+        Some(RawModeGuard::enter()?)
+    } else {
+        None
+    };
+    // 声明在原始模式守卫之后，因此会先于其被丢弃：先重置颜色，再恢复终端模式
+    let color_guard = ColorResetGuard;
+    // 一次性显示时，正文先缓存，接收完整后再输出，期间显示接收提示，推理内容不显示
+    let buffered = settings.buffered;
+    // 只显示推理内容时忽略隐藏推理内容的设置
+    let hide_reasoning = !settings.only_reasoning && (settings.hide_reasoning || buffered);
+    // 只显示推理内容时推理写入stdout，便于重定向
+    let reasoning_output = if settings.only_reasoning { Output::Stdout } else { Output::Stderr };
+    let mut held = String::new();
+    // 隐藏推理内容时，推理期间显示动态提示，正文开始时清除
+    let mut thinking = if buffered {
+        io::stderr().is_terminal().then(|| ThinkingIndicator::new(t(MsgKey::Receiving)))
+    } else {
+        settings.thinking_label()
+            .filter(|_| settings.hide_reasoning && io::stderr().is_terminal())
+            .map(ThinkingIndicator::new)
+    };
+    if buffered && let Some(indicator) = thinking.as_mut() {
+        indicator.tick()?;
+    }
 
-    // 启用原始模式以捕获键盘事件
-    let _raw_guard = RawModeGuard::enter()?;
+    // 数据块不一定在行边界处切分，不完整的行（包括被截断的多字节字符）留到下一个数据块再处理
+    let mut line_buffer: Vec<u8> = Vec::new();
+    // 解析失败的JSON片段，先与下一行拼接后再解析，仍然失败时才报告错误
+    let mut decoder = FrameDecoder::new(provider.done_marker.as_deref());
+    let mut stream_ended = false;
 
-    while let Some(chunk_result) = stream.next().await {
+    while !stream_ended {
         // 检查是否有键盘事件
-        if poll(Duration::from_millis(0))? {
-            if let Event::Key(KeyEvent { code: KeyCode::Esc, .. }) = read()? {
-                execute!(io::stdout(),
-                    Print(format!("\n{}\n", "[已中断输出]".yellow()))
-                )?;
-                break;
+        if raw_guard.is_some()
+            && poll(Duration::from_millis(0))?
+            && let Event::Key(KeyEvent { code: KeyCode::Esc, .. }) = read()? {
+            // 中断可能发生在一段彩色输出的中间，先重置颜色再打印提示
+            if styled {
+                execute!(io::stdout(), Reset)?;
             }
+            execute!(io::stderr(), Reset)?;
+            interrupted = true;
+            break;
         }
 
-        let chunk = chunk_result?;
-        let chunk_str = String::from_utf8_lossy(&chunk);
+        // 显示思考提示时按固定间隔切换动画帧，期间照常检查按键
+        let chunk_result = match thinking.as_mut() {
+            Some(indicator) if indicator.is_visible() => match tokio::time::timeout(THINKING_FRAME_INTERVAL, stream.next()).await {
+                Ok(chunk_result) => chunk_result,
+                Err(_) => {
+                    indicator.tick()?;
+                    continue;
+                },
+            },
+            // 第一个回复已经结束，提供商既不发送结束标记也不关闭连接时，等待片刻后视为数据流结束
+            _ if finish_reason.is_some() => tokio::time::timeout(FINISH_GRACE_PERIOD, stream.next()).await.unwrap_or(None),
+            _ => stream.next().await,
+        };
+
+        match chunk_result {
+            Some(Ok(chunk)) => line_buffer.extend_from_slice(&chunk),
+            None => {
+                // 数据流结束，最后一行可能没有以换行结尾
+                stream_ended = true;
+                line_buffer.push(b'\n');
+            },
+            Some(Err(e)) if reconnects < settings.reconnect_attempts => {
+                // 连接中途断开，已收到的内容作为assistant消息附加到上下文中，让模型接着输出
+                let mut retry_request = request.clone();
+                if retry_request.messages.last().is_some_and(|m| m.role == "assistant") {
+                    // 去掉预填充消息，由已收到的完整内容代替
+                    retry_request.messages.pop();
+                }
+                let received = match file_sink.as_mut() {
+                    Some(sink) => sink.read_back()?,
+                    None => reply.content().to_string(),
+                };
+                if !received.is_empty() {
+                    retry_request.messages.push(ChatMessage::new("assistant", received));
+                }
+                
+                let mut reconnected = None;
+                while reconnects < settings.reconnect_attempts {
+                    reconnects += 1;
+                    execute!(io::stderr(),
+                        Print(format!("\n{}\n", tf(MsgKey::Reconnecting, &[&e, &reconnects, &settings.reconnect_attempts]).dim())),
+                        MoveToColumn(0)
+                    )?;
+                    tokio::time::sleep(Duration::from_secs(reconnects as u64)).await;
+                    
+                    match reopen_stream(client, &provider.request_url(model_id), headers.clone(), &retry_request.to_body(provider)?).await {
+                        Ok(resp) => {
+                            reconnected = Some(resp.bytes_stream());
+                            break;
+                        },
+                        Err(retry_err) => {
+                            if settings.debug {
+                                eprintln!("[DEBUG] 重连失败: {}", retry_err);
+                            }
+                        }
+                    }
+                }
+                
+                match reconnected {
+                    Some(new_stream) => {
+                        // 新的数据流从头开始，丢弃断开前不完整的行
+                        stream = new_stream;
+                        line_buffer.clear();
+                        decoder.reset();
+                        continue;
+                    },
+                    None => {
+                        message_history.pop();
+                        return Err(ChatError::Network(e).into());
+                    },
+                }
+            },
+            Some(Err(e)) => {
+                message_history.pop();
+                return Err(ChatError::Network(e).into());
+            },
+        }
+        let Some(end) = line_buffer.iter().rposition(|&b| b == b'\n') else {
+            continue;
+        };
+        let complete: Vec<u8> = line_buffer.drain(..=end).collect();
+        let chunk_str = String::from_utf8_lossy(&complete);
         
         for line in chunk_str.lines() {
-            // 跳过空行
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            // 处理SSE格式的数据
-            let data = if line.starts_with("data: ") {
-                &line[6..]
-            } else if line.starts_with(": OPENROUTER PROCESSING") {
-                // 忽略 OpenRouter 的心跳消息
-                continue;
-            } else {
-                // 如果不是标准SSE格式，尝试直接解析整行
-                line
-            };
-            
-            if data == "[DONE]" {
-                continue;
+            // 在解析之前原样显示数据行，包括心跳、空行和错误帧
+            if settings.raw_sse {
+                let newline = if at_line_start { "" } else { "\n" };
+                execute!(io::stderr(),
+                    Print(newline),
+                    MoveToColumn(0),
+                    Print(format!("{} {}\n", "<<".dim(), line)),
+                    MoveToColumn(0)
+                )?;
+                at_line_start = true;
             }
             
-            // 尝试解析JSON响应
-            match serde_json::from_str::<ChatResponse>(data) {
-                Ok(response) => {
-                    if let Some(choice) = response.choices.first() {
-                        if let Some(reasoning) = &choice.delta.reasoning_content {
-                            total_chars += reasoning.chars().count();
-                            for c in reasoning.chars() {
-                                let mut stdout = io::stdout();
-                                if c == '\n' {
-                                    // 换行时，先重置颜色，然后打印换行符，最后移动到行首
-                                    queue!(stdout,
-                                        ResetColor,
-                                        Print("\n"),
-                                        MoveToColumn(0)
-                                    )?;
-                                } else {
-                                    queue!(stdout,
-                                        SetForegroundColor(Color::Blue),
-                                        Print(c.to_string()),
-                                        ResetColor
-                                    )?;
-                                }
-                                stdout.flush()?;
-                                let delay = tokio::time::Duration::from_millis(10);
-                                total_delay += delay;
-                                tokio::time::sleep(delay).await;
+            for frame in decoder.push_line(line) {
+                match frame {
+                    Frame::End => stream_ended = true,
+                    Frame::Responses(responses) => {
+                        for response in responses {
+                            if response.usage.is_some() {
+                                usage = response.usage;
                             }
-                        }
+                            if response_id.is_none() {
+                                response_id = response.id;
+                            }
+                            if response_model.is_none() {
+                                response_model = response.model;
+                            }
+                            for choice in &response.choices {
+                                // 请求多个回复时，其余回复先缓存，在数据流结束后分节输出
+                                if choice.index != 0 {
+                                    if let Some(content) = choice.content() {
+                                        extra_completions.entry(choice.index).or_default().push_str(content);
+                                    }
+                                    continue;
+                                }
+                                if choice.finish_reason.is_some() {
+                                    finish_reason = choice.finish_reason.clone();
+                                }
+                                if let Some(reasoning) = &choice.delta.reasoning_content {
+                                    if first_token.is_none() && !reasoning.is_empty() {
+                                        first_token = Some(request_start_time.elapsed());
+                                    }
+                                    total_chars += reasoning.chars().count();
+                                    // 完整的推理内容不受显示上限影响，开启 `--keep-reasoning` 时随回复保存
+                                    reply.push_reasoning(reasoning);
+                                    // 隐藏推理内容时只显示思考提示
+                                    let visible_reasoning = if hide_reasoning {
+                                        if let Some(indicator) = thinking.as_mut()
+                                            && !indicator.is_visible() && !reasoning.is_empty() {
+                                            indicator.tick()?;
+                                        }
+                                        ""
+                                    } else {
+                                        reasoning.as_str()
+                                    };
+                                    // 推理内容超过上限后不再显示，但仍继续读取数据流直到正文开始
+                                    let remaining = match settings.max_reasoning_chars {
+                                        0 => usize::MAX,
+                                        max => max.saturating_sub(reasoning_chars),
+                                    };
+                                    let shown: String = visible_reasoning.chars().take(remaining).collect();
+                                    if !shown.is_empty() {
+                                        // 推理内容与正文交错时，从正文切换到推理前先结束当前行
+                                        if reply.switch_to(Part::Reasoning) && !at_line_start {
+                                            execute!(io::stderr(), Print("\n"), MoveToColumn(0))?;
+                                        }
+                                        reasoning_chars += shown.chars().count();
+                                        let colored = !settings.only_reasoning || styled;
+                                        total_delay += print_char_stream(reasoning_output, &shown, Color::Blue, colored, settings.stream_delay(), &mut at_line_start).await?;
+                                    }
+                                    if shown.len() < visible_reasoning.len() && !reasoning_truncated {
+                                        reasoning_truncated = true;
+                                        execute!(io::stderr(),
+                                            Reset,
+                                            Print(format!("\n{}\n", t(MsgKey::ReasoningTruncated).dim())),
+                                            MoveToColumn(0)
+                                        )?;
+                                        at_line_start = true;
+                                    }
+                                }
                         
-                        if let Some(content) = &choice.delta.content {
-                            total_chunks += 1;
-                            total_chars += content.chars().count();
-                            for c in content.chars() {
-                                let mut stdout = io::stdout();
-                                if c == '\n' {
-                                    // 换行时，先重置颜色，然后打印换行符，最后移动到行首
-                                    queue!(stdout,
-                                        ResetColor,
-                                        Print("\n"),
-                                        MoveToColumn(0)
-                                    )?;
-                                } else {
-                                    queue!(stdout,
-                                        SetForegroundColor(Color::Green),
-                                        Print(c.to_string()),
-                                        ResetColor
-                                    )?;
+                                // 同一数据块中的推理内容总是先于正文输出，正文开始前结束推理所在的行
+                                if let Some(raw_content) = choice.content() {
+                                    let filtered = filter.push(raw_content);
+                                    let content = filtered.as_str();
+                                    if !content.is_empty() && !buffered
+                                        && let Some(indicator) = thinking.as_mut() {
+                                        indicator.clear()?;
+                                    }
+                                    if !content.is_empty() && !settings.only_reasoning
+                                        && reply.switch_to(Part::Content) && !at_line_start {
+                                        execute!(io::stderr(), Print("\n"), MoveToColumn(0))?;
+                                        at_line_start = true;
+                                    }
+                                    if first_token.is_none() && !content.is_empty() {
+                                        first_token = Some(request_start_time.elapsed());
+                                    }
+                                    total_chunks += 1;
+                                    total_chars += content.chars().count();
+                                    // 外部命令和文件接收不带颜色的原文
+                                    let piped = match (file_sink.as_mut(), pipe.as_mut()) {
+                                        (Some(sink), _) => {
+                                            sink.write(content)?;
+                                            true
+                                        },
+                                        (None, Some(output)) => match output.write(content) {
+                                            Ok(()) => true,
+                                            Err(e) => {
+                                                eprintln!("{}", tf(MsgKey::PipeWriteFailed, &[&e]).red());
+                                                pipe = None;
+                                                false
+                                            },
+                                        },
+                                        (None, None) => false,
+                                    };
+                                    if settings.only_reasoning {
+                                        // 正文照常保存，但不显示
+                                    } else if !piped && buffered {
+                                        held.push_str(content);
+                                    } else if !piped {
+                                        let segments = match markdown.as_mut() {
+                                            Some(renderer) => renderer.push(content),
+                                            None => vec![Segment::plain(content)],
+                                        };
+                                        total_delay += print_streamed(&segments, styled, settings.stream_delay(), false, &mut at_line_start).await?;
+                                    }
+                                    if file_sink.is_none() {
+                                        reply.push_content(content);
+                                    }
+                            
+                                    if settings.debug {
+                                        eprintln!("[DEBUG] 处理{}个数据块耗时: {:?}", total_chunks, process_start_time.elapsed());
+                                        eprintln!("[DEBUG] 已处理{}个字符，累计输出延迟: {:?}", total_chars, total_delay);
+                                        process_start_time = tokio::time::Instant::now();
+                                    }
+                                
+                                    if filter.is_stopped() {
+                                        stream_ended = true;
+                                        break;
+                                    }
+                                }
+                        
+                                if let Some(deltas) = &choice.delta.tool_calls {
+                                    accumulate_tool_calls(&mut tool_calls, deltas);
                                 }
-                                stdout.flush()?;
-                                let delay = tokio::time::Duration::from_millis(10);
-                                total_delay += delay;
-                                tokio::time::sleep(delay).await;
                             }
-                            full_response.push_str(content);
-                            
-                            if env::var("HAMBUR_DEBUG").is_ok() {
-                                eprintln!("[DEBUG] 处理{}个数据块耗时: {:?}", total_chunks, process_start_time.elapsed());
-                                eprintln!("[DEBUG] 已处理{}个字符，累计输出延迟: {:?}", total_chars, total_delay);
-                                process_start_time = tokio::time::Instant::now();
+                            if stream_ended {
+                                break;
                             }
                         }
-                    }
-                },
-                Err(e) => {
-                    if env::var("HAMBUR_DEBUG").is_ok() {
-                        eprintln!("[DEBUG] JSON解析错误: {}, 数据: {}", e, data);
-                    }
-                    
-                    // 尝试其他可能的响应格式
-                    if !data.starts_with('{') && !data.starts_with('[') {
-                        // 如果不是JSON格式，直接显示文本内容
-                        for c in data.chars() {
-                            if c == '\n' {
-                                // 换行时，先重置颜色，然后打印换行符，最后移动到行首
-                                execute!(io::stdout(),
-                                    ResetColor,
-                                    Print("\n"),
-                                    MoveToColumn(0)
-                                )?;
-                            } else {
-                                execute!(io::stdout(),
-                                    SetForegroundColor(Color::Green),
-                                    Print(c.to_string()),
-                                    ResetColor
-                                )?;
-                            }
+                    },
+                    // 本轮对话失败，移除刚加入的用户消息，保持历史记录中的消息成对出现
+                    Frame::Error(error) => {
+                        if let Some(indicator) = thinking.as_mut() {
+                            indicator.clear()?;
                         }
-                        full_response.push_str(data);
-                    } else {
-                        // 如果是JSON格式但解析失败，可能是错误响应，直接显示
-                        let error_msg = format!("解析响应失败: {}\n原始数据: {}", e, data);
-                        for c in error_msg.chars() {
-                            if c == '\n' {
-                                // 换行时，先重置颜色，然后打印换行符，最后移动到行首
-                                execute!(io::stdout(),
-                                    ResetColor,
-                                    Print("\n"),
-                                    MoveToColumn(0)
-                                )?;
-                            } else {
-                                execute!(io::stdout(),
-                                    SetForegroundColor(Color::Red),
-                                    Print(c.to_string()),
-                                    ResetColor
-                                )?;
-                            }
+                        message_history.pop();
+                        return Err(ChatError::Api(error.to_string()).into());
+                    },
+                    // 不是JSON格式的数据直接作为回复文本显示
+                    Frame::Text(text) => {
+                        if let Some(indicator) = thinking.as_mut() {
+                            indicator.clear()?;
                         }
-                        full_response.push_str(&error_msg);
-                    }
+                        if let Some(output) = pipe.as_mut() && output.write(&text).is_ok() {
+                            reply.push_content(&text);
+                            continue;
+                        }
+                        print_char_stream(Output::Stdout, &text, Color::Green, styled, Duration::ZERO, &mut at_line_start).await?;
+                        reply.push_content(&text);
+                    },
+                    // 无法解析的JSON直接显示，但不计入回复内容
+                    Frame::Invalid { message, data } => {
+                        if settings.debug {
+                            eprintln!("[DEBUG] JSON解析错误: {}, 数据: {}", message, data);
+                        }
+                        if let Some(indicator) = thinking.as_mut() {
+                            indicator.clear()?;
+                        }
+                        print_parse_error(&message, &data)?;
+                        at_line_start = true;
+                    },
+                }
+                if stream_ended {
+                    break;
                 }
             }
+            if stream_ended {
+                break;
+            }
         }
     }
+    
+    // 只有推理内容没有正文（或被中断）时，思考提示仍在显示
+    if let Some(indicator) = thinking.as_mut() {
+        indicator.clear()?;
+    }
+    
+    // 一次性输出缓存的正文，中断时输出已收到的部分
+    if !held.is_empty() {
+        let segments = match markdown.as_mut() {
+            Some(renderer) => renderer.push(&held),
+            None => vec![Segment::plain(&held)],
+        };
+        print_streamed(&segments, styled, Duration::ZERO, false, &mut at_line_start).await?;
+    }
+    
+    // 数据流结束时仍未拼接完整的片段
+    if let Some(Frame::Invalid { message, data }) = decoder.finish() {
+        print_parse_error(&message, &data)?;
+        at_line_start = true;
+    }
 
     // 恢复终端模式会通过RawModeGuard的Drop实现自动处理
+
+    // 回复比前缀还短时，缓冲的内容原样输出；末尾与停止标记开头相同但没有构成完整标记的内容同样输出
+    let rest = filter.finish();
+    if !rest.is_empty() && let Some(sink) = file_sink.as_mut() {
+        sink.write(&rest)?;
+    } else if !rest.is_empty() {
+        match pipe.as_mut() {
+            Some(output) => {
+                let _ = output.write(&rest);
+            },
+            None if settings.only_reasoning => {},
+            None => {
+                print_streamed(&[Segment::plain(&rest)], styled, Duration::ZERO, false, &mut at_line_start).await?;
+            },
+        }
+        reply.push_content(&rest);
+    }
+
+    let mut streamed_chars = 0;
+    if let Some(sink) = file_sink.take() {
+        streamed_chars = sink.finish()?;
+        if let Some(path) = &settings.stream_to_file {
+            execute!(io::stderr(),
+                Print(format!("{}\n", tf(MsgKey::StreamFileDone, &[&streamed_chars, &path.display()]).dim())),
+                MoveToColumn(0)
+            )?;
+        }
+    }
+
+    // 关闭外部命令的输入，等待其输出完剩余的内容
+    if let Some(output) = pipe.take() {
+        // 外部命令直接写入终端，等待前先重置颜色并退出原始模式，否则其输出换行时不会回到行首
+        drop(color_guard);
+        drop(raw_guard);
+        match output.finish() {
+            Ok(status) if !status.success() => {
+                eprintln!("{}", tf(MsgKey::PipeExitStatus, &[&status]).yellow());
+            },
+            Ok(_) => {},
+            Err(e) => eprintln!("{}", tf(MsgKey::PipeWriteFailed, &[&e]).red()),
+        }
+    }
+
+    if let Some(stats) = options.stats {
+        stats.set(StreamStats {
+            first_token,
+            elapsed: request_start_time.elapsed(),
+            chars: total_chars,
+            completion_tokens: usage.map(|usage| usage.completion_tokens),
+        });
+    }
     
-    println!();
+    // 输出Markdown渲染器中最后一行未换行的内容
+    if let Some(renderer) = markdown.as_mut() {
+        print_streamed(&renderer.finish(), styled, Duration::ZERO, false, &mut at_line_start).await?;
+    }
     
-    if env::var("HAMBUR_DEBUG").is_ok() {
-        eprintln!("[DEBUG] 总耗时: {:?}", start_time.elapsed());
+    // 回复没有以换行结尾时先结束当前行（写入stdout，使重定向的内容以换行结尾），
+    // 再在stderr输出一个空行，与下一轮的提示符隔开
+    if !at_line_start {
+        if styled {
+            execute!(io::stdout(), Print("\n"), MoveToColumn(0))?;
+        } else {
+            println!();
+        }
+    }
+    execute!(io::stderr(), Print("\n"), MoveToColumn(0))?;
+
+    // 其余回复各自带标签输出，只有第一个回复会保存到历史记录中
+    let total_completions = settings.completions.max(extra_completions.len() as u32 + 1);
+    for (index, content) in &extra_completions {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", tf(MsgKey::CompletionLabel, &[&(index + 1), &total_completions]).dim())),
+            MoveToColumn(0)
+        )?;
+        let newline = if content.ends_with('\n') { "" } else { "\n" };
+        if styled {
+            // 原始模式下换行不会回到行首
+            let text = format!("{}{}", content, newline).replace('\n', "\r\n");
+            execute!(io::stdout(), Print(text.green()))?;
+        } else {
+            print!("{}{}", content, newline);
+        }
+        execute!(io::stderr(), Print("\n"), MoveToColumn(0))?;
     }
     
-    message_history.push(ChatMessage {
-        role: "assistant".to_string(),
-        content: full_response.clone(),
-    });
+    // 只展示模型请求的工具调用，不会执行
+    for call in &tool_calls {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            SetColor(Color::Magenta),
+            Print(format!("{}\n", tf(MsgKey::ToolCall, &[&call.name, &call.arguments]))),
+            Reset,
+            MoveToColumn(0)
+        )?;
+    }
     
-    Ok(full_response)
-}
-
-async fn interactive_mode(client: &reqwest::Client) -> Result<()> {
-    println!("{}", "欢迎使用Hambur，输入'exit'退出，'clear'清空聊天记录，直接输入模型关键字切换模型，连续按两次ESC退出程序".blue().bold());
+    // 未配置价格或接口未返回用量时不显示费用
+    if let (Some(pricing), Some(usage)) = (pricing, usage) {
+        let cost = pricing.estimate(&usage);
+        cost::record(cost);
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", tf(MsgKey::CostEstimate, &[&format!("{:.4}", cost)]).dim())),
+            MoveToColumn(0)
+        )?;
+    }
     
-    let mut message_history: Vec<ChatMessage> = Vec::new();
-    let mut current_model = String::from("google/gemini-2.0-flash-001"); // 默认使用gemini-flash
+    // 提供商（例如OpenRouter）可能在不报错的情况下改用其他模型
+    if settings.verify_model
+        && let Some(returned) = &response_model
+        && !is_same_model(model_id, returned) {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", tf(MsgKey::ModelSubstituted, &[&model_id, returned]).yellow())),
+            MoveToColumn(0)
+        )?;
+    }
     
-    // 用于跟踪ESC按键
-    let mut last_esc_time: Option<std::time::Instant> = None;
+    if settings.debug {
+        if let Some(id) = &response_id {
+            eprintln!("[DEBUG] 响应ID: {}", id);
+        }
+        eprintln!("[DEBUG] 总耗时: {:?}", start_time.elapsed());
+    }
     
-    'outer: loop {
-        execute!(io::stdout(),
+    // 达到长度上限时提示可以续写
+    if finish_reason.as_deref() == Some("length") {
+        execute!(io::stderr(),
             MoveToColumn(0),
-            Print(format!("{} ", "你:".cyan().bold()))
+            Print(format!("{}\n", t(MsgKey::ReplyTruncated).yellow())),
+            MoveToColumn(0)
         )?;
-        io::stdout().flush()?;
-        
-        // 启用原始模式以捕获键盘事件
-        let _raw_guard = RawModeGuard::enter()?;
-        
-        let mut input = String::new();
-        let mut reading = true;
-        
-        while reading {
-            if poll(Duration::from_millis(100))? {
-                match read()? {
-                    Event::Key(KeyEvent { code: KeyCode::Esc, .. }) => {
-                        // 检查是否是连续两次ESC
-                        let now = std::time::Instant::now();
-                        if let Some(last_time) = last_esc_time {
-                            // 如果两次ESC按键间隔小于500毫秒，则退出程序
-                            if now.duration_since(last_time).as_millis() < 500 {
-                                execute!(io::stdout(),
-                                    MoveToColumn(0),
-                                    Print("\n[连续按两次ESC，程序已退出]\n"),
-                                    ResetColor
-                                )?;
-                                return Ok(());
-                            }
-                        }
-                        last_esc_time = Some(now);
-                    },
-                    Event::Key(KeyEvent { code: KeyCode::Enter, .. }) => {
-                        execute!(io::stdout(), Print("\n"), MoveToColumn(0))?;
-                        reading = false;
-                    },
-                    Event::Key(KeyEvent { code, .. }) => {
-                        // 重置ESC计时器
-                        last_esc_time = None;
-                        
-                        // 处理其他按键
-                        match code {
-                            KeyCode::Char(c) => {
-                                input.push(c);
-                                print!("{}", c);
-                                io::stdout().flush()?;
-                            },
-                            KeyCode::Backspace => {
-                                if !input.is_empty() {
-                                    input.pop();
-                                    // 删除一个字符（退格、空格、再退格）
-                                    execute!(io::stdout(),
-                                        Print("\u{8} \u{8}")
-                                    )?;
-                                    io::stdout().flush()?;
-                                }
-                            },
-                            _ => {}
-                        }
-                    },
-                    _ => {}
-                }
+    }
+    
+    // 模型没有返回任何内容时给出提示，不把空回复计入上下文；
+    // 同时移除本轮的用户消息，保持历史记录中的消息成对出现
+    let full_response = reply.content().to_string();
+    if full_response.is_empty() && streamed_chars == 0 && tool_calls.is_empty() && !interrupted {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", t(MsgKey::EmptyResponse).yellow())),
+            MoveToColumn(0)
+        )?;
+        message_history.pop();
+        return Ok(full_response);
+    }
+    
+    // 被中断时已输出的部分内容仍然保存到历史记录中
+    if settings.only_reasoning && !reply.has_reasoning() {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", t(MsgKey::NoReasoning).yellow())),
+            MoveToColumn(0)
+        )?;
+    }
+    message_history.push(reply.into_message(settings.keep_reasoning || settings.only_reasoning));
+    
+    if interrupted {
+        return Err(ChatError::Interrupted.into());
+    }
+    Ok(full_response)
+}
+
+/// 逐字输出的位置：正文写入stdout，推理内容和错误信息写入stderr
+#[derive(Debug, Clone, Copy)]
+enum Output {
+    Stdout,
+    Stderr,
+}
+
+/// 输出一个带颜色的字符并立即刷新
+/// 换行时先重置颜色，再换行并回到行首（原始模式下换行不会回到行首）；`styled` 为false（被重定向）时不输出控制符
+fn print_char(output: Output, c: StyledContent<char>, styled: bool) -> Result<()> {
+    fn write(out: &mut impl Write, c: StyledContent<char>, styled: bool) -> Result<()> {
+        let plain = *c.content();
+        if !styled {
+            queue!(out, Print(plain))?;
+        } else if plain == '\n' {
+            queue!(out, Reset, Print("\n"), MoveToColumn(0))?;
+        } else {
+            queue!(out, Print(c))?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+    match output {
+        Output::Stdout => write(&mut io::stdout(), c, styled),
+        Output::Stderr => write(&mut io::stderr(), c, styled),
+    }
+}
+
+/// 以打字机效果逐字输出一段单色的文本，每个字符之后等待 `delay`，返回累计等待的时间
+async fn print_char_stream(output: Output, text: &str, color: Color, styled: bool, delay: Duration, at_line_start: &mut bool) -> Result<Duration> {
+    let mut total = Duration::ZERO;
+    for c in text.chars() {
+        print_char(output, c.color(color), styled)?;
+        *at_line_start = c == '\n';
+        if !delay.is_zero() {
+            total += delay;
+            tokio::time::sleep(delay).await;
+        }
+    }
+    Ok(total)
+}
+
+/// 以打字机效果逐字输出带样式的正文，每个字符之后等待 `delay`，返回累计等待的时间
+/// `skippable` 时按任意键跳过剩余的等待，立即输出其余内容；需要在原始模式下调用才能及时读到按键
+async fn print_streamed(segments: &[Segment], styled: bool, mut delay: Duration, skippable: bool, at_line_start: &mut bool) -> Result<Duration> {
+    let mut total = Duration::ZERO;
+    for segment in segments {
+        for c in segment.text.chars() {
+            print_char(Output::Stdout, segment.style.apply(c), styled)?;
+            *at_line_start = c == '\n';
+            if delay.is_zero() {
+                continue;
+            }
+            if skippable && poll(Duration::from_millis(0))? && matches!(read()?, Event::Key(_)) {
+                delay = Duration::ZERO;
+                continue;
             }
+            total += delay;
+            tokio::time::sleep(delay).await;
         }
-        
-        // 恢复终端模式会通过RawModeGuard的Drop实现自动处理
-        
-        let input = input.trim();
-        if input.eq_ignore_ascii_case("exit") {
-            break;
-        } else if input.eq_ignore_ascii_case("clear") {
-            // 清空聊天记录
-            message_history.clear();
-            execute!(io::stdout(),
-                MoveToColumn(0),
-                Print(format!("{}", "[聊天记录已清空]\n".yellow()))
-            )?;
-            continue;
-        } else {
-            // 先尝试查找匹配的模型
-            let matches = find_models(input);
-            
-            if !matches.is_empty() {
-                // 找到匹配的模型
-                match matches.len() {
-                    0 => {}, // 不可能发生，因为前面已经检查过matches不为空
-                    1 => {
-                        // 只有一个匹配，直接切换
-                        let model = &matches[0];
-                        current_model = model.id.clone();
-                        execute!(io::stdout(),
-                            MoveToColumn(0),
-                            SetForegroundColor(Color::Green),
-                            Print("已切换到模型: "),
-                            Print(model.name.clone()),
-                            Print("\n"),
-                            ResetColor
-                        )?;
-                        continue;
-                    },
-                    _ => {
-                        // 多个匹配，使用上下方向键选择
-                        execute!(io::stdout(),
-                            MoveToColumn(0),
-                            Print(format!("{}", "找到多个匹配的模型，请使用上下方向键选择:\n".yellow()))
-                        )?;
-                        
-                        // 启用原始模式以捕获键盘事件
-                        let _raw_guard = RawModeGuard::enter()?;
-                        
-                        let mut selected_index = 0;
-                        let mut selected = false;
-                        
-                        // 显示初始选择
-                        for (i, model) in matches.iter().enumerate() {
-                            if i == selected_index {
-                                execute!(io::stdout(),
-                                    MoveToColumn(0),
-                                    Print(format!("{} {} ({})", ">".green(), model.name, model.provider)),
-                                    Print("\n")
-                                )?;
-                            } else {
-                                execute!(io::stdout(),
+    }
+    Ok(total)
+}
+
+/// 以打字机效果重新输出最后一条回复，不发送请求，按任意键跳过剩余的动画
+async fn replay_last_reply(message_history: &[ChatMessage], settings: &Settings, label: &str) -> Result<()> {
+    let Some(reply) = message_history.iter().rev().find(|m| m.role == "assistant") else {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", t(MsgKey::ReplayEmpty).yellow()))
+        )?;
+        return Ok(());
+    };
+
+    execute!(io::stderr(), MoveToColumn(0), Print(format!("{} ", label.green().bold())))?;
+    let styled = io::stdout().is_terminal();
+    let mut segments = Vec::new();
+    if settings.markdown && styled {
+        let mut renderer = MarkdownRenderer::new();
+        segments.extend(renderer.push(&reply.content));
+        segments.extend(renderer.finish());
+    } else {
+        segments.push(Segment::plain(&reply.content));
+    }
+
+    let mut at_line_start = true;
+    {
+        let _raw_guard = if io::stdin().is_terminal() { Some(RawModeGuard::enter()?) } else { None };
+        let _color_guard = ColorResetGuard;
+        print_streamed(&segments, styled, settings.stream_delay(), true, &mut at_line_start).await?;
+    }
+    if !at_line_start {
+        println!();
+    }
+    execute!(io::stderr(), Print("\n"), MoveToColumn(0))?;
+    Ok(())
+}
+
+/// 在stderr中以红色显示无法解析的数据，不计入回复内容
+fn print_parse_error(message: &str, data: &str) -> Result<()> {
+    let error_msg = ChatError::Parse { message: message.to_string(), data: data.to_string() }.to_string();
+    for c in error_msg.chars() {
+        print_char(Output::Stderr, c.color(Color::Red), true)?;
+    }
+    execute!(io::stderr(), Print("\n"), MoveToColumn(0))?;
+    Ok(())
+}
+
+/// 输出一轮对话失败的原因，不属于 `ChatError` 的错误原样返回
+fn report_chat_error(error: anyhow::Error) -> Result<()> {
+    match error.downcast_ref::<ChatError>() {
+        Some(ChatError::Interrupted) => {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", ChatError::Interrupted.to_string().yellow())),
+                MoveToColumn(0)
+            )?;
+            Ok(())
+        },
+        Some(chat_error) => {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", chat_error.to_string().red())),
+                MoveToColumn(0)
+            )?;
+            Ok(())
+        },
+        None => Err(error),
+    }
+}
+
+/// 批处理模式：逐行读取stdin，每个非空行作为一个问题发送给默认模型，读到EOF时结束
+/// 某一行失败时输出错误并继续处理后续行，全部处理完后以非零状态码退出
+async fn stdin_loop(client: &reqwest::Client, providers: &[ModelProvider], settings: &Settings, stateful: bool, max_concurrent: usize) -> Result<()> {
+    if !stateful && max_concurrent > 1 {
+        return stdin_loop_concurrent(client, providers, settings, max_concurrent).await;
+    }
+    
+    let mut message_history = Vec::new();
+    let mut failed = false;
+    
+    for line in io::stdin().lines() {
+        let line = line.context(t(MsgKey::StdinReadFailed))?;
+        let prompt = line.trim();
+        if prompt.is_empty() {
+            continue;
+        }
+        if !stateful {
+            message_history.clear();
+        }
+        
+        if let Err(e) = send_with_hooks(client, providers, prompt, &settings.model, &mut message_history, settings, TurnOptions::default()).await {
+            report_chat_error(e)?;
+            failed = true;
+        }
+        println!("---");
+    }
+    
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// 并发处理stdin中的各行问题，同时进行的请求不超过 `max_concurrent` 个，其余问题留在stdin中，有空位时才读取
+/// 每个问题与逐行模式一样执行钩子，失败时改用备用模型；回答不逐字输出，而是缓冲到完整后按输入的顺序输出，
+/// 因此前面的问题较慢时，后面已经完成的回答也要等待；回答之间仍以 `---` 分隔
+async fn stdin_loop_concurrent(client: &reqwest::Client, providers: &[ModelProvider], settings: &Settings, max_concurrent: usize) -> Result<()> {
+    let lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    let mut replies = std::pin::pin!(futures::stream::unfold(lines, |mut lines| async move {
+            lines.next_line().await.transpose().map(|line| (line, lines))
+        })
+        .filter(|line| std::future::ready(!line.as_ref().is_ok_and(|line| line.trim().is_empty())))
+        .map(|line| async move {
+            let line = line.context(t(MsgKey::StdinReadFailed))?;
+            anyhow::Ok(fetch_with_hooks(client, providers, line.trim(), settings).await)
+        })
+        .buffered(max_concurrent));
+    
+    let mut failed = false;
+    while let Some(reply) = replies.next().await {
+        match reply? {
+            Ok(reply) => println!("{}", reply.trim_end_matches('\n')),
+            Err(e) => {
+                report_chat_error(e)?;
+                failed = true;
+            },
+        }
+        println!("---");
+    }
+    
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// 不带聊天记录地请求一个问题的完整回复，与 `send_with_hooks` 一样执行钩子并在失败时改用备用模型
+async fn fetch_with_hooks(client: &reqwest::Client, providers: &[ModelProvider], prompt: &str, settings: &Settings) -> Result<String> {
+    let message = match &settings.hooks.pre_send {
+        Some(command) => run_hook("pre_send", command, prompt).await?,
+        None => prompt.to_string(),
+    };
+    let mut tried = vec![settings.model.clone()];
+    let reply = loop {
+        let current = tried.last().map(String::as_str).unwrap_or(&settings.model);
+        let error = match fetch_reply(client, providers, current, vec![ChatMessage::new("user", message.as_str())], settings).await {
+            Err(e) if e.downcast_ref::<ChatError>().is_some_and(ChatError::is_transient) => e,
+            result => break result?,
+        };
+        switch_to_fallback(providers, settings, &mut tried, error)?;
+    };
+    match &settings.hooks.post_receive {
+        Some(command) => run_hook("post_receive", command, &reply).await,
+        None => Ok(reply),
+    }
+}
+
+/// 发送一轮对话，前后执行配置的钩子：`pre_send` 处理要发送的消息，`post_receive` 处理完整的回复
+/// 处理后的回复与原回复不同时另外输出，并代替原回复保存到聊天记录中；钩子执行失败时给出警告并使用原始内容
+async fn send_with_hooks(client: &reqwest::Client, providers: &[ModelProvider], message: &str, model_id: &str, message_history: &mut Vec<ChatMessage>, settings: &Settings, options: TurnOptions<'_>) -> Result<String> {
+    let message = match &settings.hooks.pre_send {
+        Some(command) => run_hook("pre_send", command, message).await?,
+        None => message.to_string(),
+    };
+    let response = send_with_fallback(client, providers, &message, model_id, message_history, settings, options).await?;
+    // 写入文件的回复不保留在内存中，无法处理
+    let Some(command) = settings.hooks.post_receive.as_ref().filter(|_| settings.stream_to_file.is_none()) else {
+        return Ok(response);
+    };
+
+    let processed = run_hook("post_receive", command, &response).await?;
+    if processed != response {
+        if let Some(reply) = message_history.last_mut().filter(|m| m.role == "assistant") {
+            reply.content = processed.clone();
+        }
+        execute!(io::stderr(), MoveToColumn(0), Print(format!("{}\n", t(MsgKey::HookProcessed).dim())))?;
+        execute!(io::stdout(), MoveToColumn(0), Print(&processed), Print("\n"), MoveToColumn(0))?;
+        execute!(io::stderr(), Print("\n"), MoveToColumn(0))?;
+    }
+    Ok(processed)
+}
+
+/// 发送一轮对话，因网络错误、5xx 或 429 失败时依次改用配置的备用模型重新发送
+/// 失败的请求不会留在聊天记录中，备用模型看到的是同样的上下文；链中出现重复的模型时停止
+async fn send_with_fallback(client: &reqwest::Client, providers: &[ModelProvider], message: &str, model_id: &str, message_history: &mut Vec<ChatMessage>, settings: &Settings, options: TurnOptions<'_>) -> Result<String> {
+    let mut tried = vec![model_id.to_string()];
+    loop {
+        let current = tried.last().map(String::as_str).unwrap_or(model_id);
+        let error = match send_chat_request(client, providers, message, current, message_history, settings, options).await {
+            Err(e) if e.downcast_ref::<ChatError>().is_some_and(ChatError::is_transient) => e,
+            result => return result,
+        };
+        switch_to_fallback(providers, settings, &mut tried, error)?;
+    }
+}
+
+/// 最后尝试的模型失败后改用它的备用模型：没有未尝试过的备用模型时返回原来的错误，
+/// 否则报告错误并提示切换，备用模型加入 `tried` 的末尾
+fn switch_to_fallback(providers: &[ModelProvider], settings: &Settings, tried: &mut Vec<String>, error: anyhow::Error) -> Result<()> {
+    let current = tried.last().map(String::as_str).unwrap_or(&settings.model);
+    let Some(next) = settings.fallbacks.get(current).filter(|next| !tried.contains(next)).cloned() else {
+        return Err(error);
+    };
+
+    report_chat_error(error)?;
+    let name = providers.iter()
+        .flat_map(|p| &p.models)
+        .find(|m| m.id == next)
+        .map_or(next.as_str(), |m| m.name.as_str());
+    execute!(io::stderr(),
+        MoveToColumn(0),
+        Print(format!("{}\n", tf(MsgKey::FallbackSwitch, &[&name]).dim()))
+    )?;
+    tried.push(next);
+    Ok(())
+}
+
+/// 执行一个钩子，失败时给出警告并原样返回输入
+/// 钩子命令会阻塞等待子进程，在阻塞线程池中执行，不影响同时进行的其他请求
+async fn run_hook(name: &str, command: &str, input: &str) -> Result<String> {
+    let task = {
+        let (command, input) = (command.to_string(), input.to_string());
+        tokio::task::spawn_blocking(move || hooks::run(&command, &input))
+    };
+    let result = task.await?;
+    match result {
+        Ok(output) => Ok(output),
+        Err(e) => {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", tf(MsgKey::HookFailed, &[&name, &format!("{:#}", e)]).yellow()))
+            )?;
+            Ok(input.to_string())
+        },
+    }
+}
+
+/// 发送一组消息并读取完整的回复，不显示流式输出，用于并发请求和生成标题
+/// 与交互模式一样附加系统提示词和采样参数，遵守提供商的客户端限流
+async fn fetch_reply(client: &reqwest::Client, providers: &[ModelProvider], model_id: &str, messages: Vec<ChatMessage>, settings: &Settings) -> Result<String> {
+    let provider = get_provider_by_model(providers, model_id)
+        .context(tf(MsgKey::ModelProviderNotFound, &[&model_id]))?;
+    let api_key = resolve_api_key(provider)?;
+    let mut headers = headers::build_headers(&api_key, provider)?;
+    headers::insert_extra(&mut headers, &settings.extra_headers)?;
+    
+    let mut request = ChatRequest {
+        model: model_id.to_string(),
+        messages,
+        stream: true,
+        n: None,
+        seed: settings.seed,
+        temperature: settings.sampling().temperature,
+        top_p: settings.sampling().top_p,
+        stream_options: None,
+    };
+    request.messages.splice(0..0, settings.preamble());
+    let body = request.to_body(provider)?;
+    if let Some(path) = &settings.dump_requests
+        && let Err(e) = dump::append_request(path, &provider.api_base, &headers, &body) {
+        eprintln!("{}", tf(MsgKey::DumpRequestFailed, &[&path.display(), &e]).red());
+    }
+    
+    if let Some(rpm) = provider.rate_limit_rpm {
+        let wait = ratelimit::LIMITER.reserve(&provider.name, rpm);
+        tokio::time::sleep(wait).await;
+    }
+    
+    let response = client
+        .post(provider.request_url(model_id))
+        .headers(headers)
+        .json(&body)
+        .send()
+        .await
+        .map_err(ChatError::from_request_error)?;
+    let status = response.status().as_u16();
+    if !response.status().is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(ChatError::from_status(status, error_text, &provider.api_key_env).into());
+    }
+    let text = response.text().await.map_err(ChatError::Network)?;
+    
+    let filter = ContentFilter::new(settings.strip_prefix.as_deref(), settings.client_stop.as_deref());
+    reply_from_sse(&text, provider.done_marker.as_deref(), filter)
+}
+
+/// 从完整的SSE响应中取出第一个回复的正文
+/// 与流式显示使用相同的解码和过滤：遵守结束标记，拼接被拆分的JSON，去掉前缀并在停止标记处截断，
+/// 无法解析的数据报告到stderr
+fn reply_from_sse(text: &str, done_marker: Option<&str>, mut filter: ContentFilter) -> Result<String> {
+    let mut decoder = FrameDecoder::new(done_marker);
+    let mut reply = String::new();
+    'lines: for line in text.lines() {
+        for frame in decoder.push_line(line) {
+            match frame {
+                Frame::End => break 'lines,
+                Frame::Responses(responses) => {
+                    for choice in responses.iter().flat_map(|response| &response.choices).filter(|choice| choice.index == 0) {
+                        if let Some(content) = choice.content() {
+                            reply.push_str(&filter.push(content));
+                        }
+                    }
+                },
+                Frame::Error(error) => return Err(ChatError::Api(error.to_string()).into()),
+                Frame::Text(text) => reply.push_str(&text),
+                Frame::Invalid { message, data } => print_parse_error(&message, &data)?,
+            }
+            if filter.is_stopped() {
+                break 'lines;
+            }
+        }
+    }
+    if let Some(Frame::Invalid { message, data }) = decoder.finish() {
+        print_parse_error(&message, &data)?;
+    }
+    reply.push_str(&filter.finish());
+    Ok(reply)
+}
+
+/// 测速模式：将同一个问题独立发送 `runs` 次，结束后输出每轮的首字延迟、耗时和速度及其平均值
+/// 失败的轮次不计入统计，全部完成后有失败时以非零状态码退出
+async fn run_bench(client: &reqwest::Client, providers: &[ModelProvider], settings: &Settings, prompt: &str, runs: u32) -> Result<()> {
+    let mut results = Vec::new();
+    let mut failed = false;
+
+    for run in 1..=runs {
+        eprintln!("{}", tf(MsgKey::BenchProgress, &[&run, &runs]).dim());
+        let stats = Cell::new(StreamStats::default());
+        let options = TurnOptions { stats: Some(&stats), ..TurnOptions::default() };
+        let mut message_history = Vec::new();
+        match send_chat_request(client, providers, prompt, &settings.model, &mut message_history, settings, options).await {
+            Ok(_) => results.push(stats.get()),
+            Err(e) => {
+                report_chat_error(e)?;
+                failed = true;
+            },
+        }
+    }
+
+    print!("{}", bench::summary_table(&results));
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// 流中断后重新发送请求，只有HTTP状态码为成功时才返回响应
+async fn reopen_stream(client: &reqwest::Client, url: &str, headers: HeaderMap, body: &serde_json::Value) -> Result<reqwest::Response> {
+    let resp = client
+        .post(url)
+        .headers(headers)
+        .json(body)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(resp)
+}
+
+async fn interactive_mode(client: &reqwest::Client, providers: &mut Vec<ModelProvider>, settings: &mut Settings, cli: &Cli) -> Result<()> {
+    eprintln!("{}", t(MsgKey::Welcome).blue().bold());
+    
+    let mut message_history: Vec<ChatMessage> = Vec::new();
+    let mut current_model = settings.model.clone();
+    // 配置文件没有指定默认模型时，沿用上次退出时的模型
+    if current_model == DEFAULT_MODEL
+        && let Some(last_model) = read_last_model()
+        && get_provider_by_model(providers, &last_model).is_some() {
+        current_model = last_model;
+    }
+    // 通过 `/tab` 管理的其他对话，当前对话的聊天记录和模型仍保存在上面两个变量中
+    let mut tabs = Tabs::default();
+    // 下一条消息使用的预填充文本
+    let mut pending_prefill: Option<String> = None;
+    
+    // 编写模式：回车只换行，通过 /send 或 Ctrl+D 提交
+    let mut compose_mode = false;
+    
+    // 最近一次 `/models` 显示的列表，`/model <序号>` 按其中的顺序选择
+    let mut model_listing: Vec<Model> = Vec::new();
+    
+    // 通过上下方向键找回之前的输入
+    let mut input_history = InputHistory::load(settings.input_history);
+    
+    // 用于跟踪ESC按键
+    let mut last_esc_time: Option<std::time::Instant> = None;
+    
+    'session: loop {
+        // 在每轮提示符前画一条横跨终端宽度的分隔线
+        if settings.separators && io::stderr().is_terminal() {
+            let (columns, _) = size()?;
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", "─".repeat(columns as usize).dim())),
+                MoveToColumn(0)
+            )?;
+        }
+        let prompt = input_prompt(settings, providers, &current_model, message_history.len());
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{} ", prompt.as_str().cyan().bold()))
+        )?;
+        io::stderr().flush()?;
+        
+        // 启用原始模式以捕获键盘事件
+        let _raw_guard = RawModeGuard::enter()?;
+        
+        let mut input = String::new();
+        let mut reading = true;
+        // 最近一次按键的时间，用于空闲时自动清空聊天记录
+        let mut last_activity = std::time::Instant::now();
+        // 正在显示的历史记录序号，以及开始翻阅历史前未提交的输入
+        let mut recall_index: Option<usize> = None;
+        let mut draft = String::new();
+        
+        while reading {
+            if poll(Duration::from_millis(100))? {
+                last_activity = std::time::Instant::now();
+                match read()? {
+                    Event::Key(KeyEvent { code: KeyCode::Esc, .. }) => {
+                        // 检查是否是连续两次ESC
+                        let now = std::time::Instant::now();
+                        if let Some(last_time) = last_esc_time {
+                            // 如果两次ESC按键间隔小于设置的时间窗口，则退出程序
+                            if now.duration_since(last_time) < settings.esc_timeout() {
+                                execute!(io::stderr(),
                                     MoveToColumn(0),
-                                    Print(format!("  {} ({})", model.name, model.provider)),
-                                    Print("\n")
+                                    Print(format!("\n{}\n", t(MsgKey::DoubleEscExit))),
+                                    Reset
                                 )?;
+                                break 'session;
                             }
                         }
+                        last_esc_time = Some(now);
+                    },
+                    Event::Key(KeyEvent { code: KeyCode::Enter, .. }) => {
+                        // 两次ESC之间按下其他任何键（包括回车）都不算连续按键
+                        last_esc_time = None;
+                        execute!(io::stderr(), Print("\n"), MoveToColumn(0))?;
+                        if !compose_mode {
+                            reading = false;
+                            continue;
+                        }
+                        // 编写模式：回车换行，单独一行的 /send 提交整个缓冲区，单独输入 /compose 退出编写模式
+                        let line_start = input.rfind('\n').map_or(0, |i| i + 1);
+                        match input[line_start..].trim() {
+                            "/send" => {
+                                input.truncate(line_start.saturating_sub(1));
+                                reading = false;
+                            },
+                            "/compose" if line_start == 0 => reading = false,
+                            _ => input.push('\n'),
+                        }
+                    },
+                    Event::Key(KeyEvent { code: KeyCode::Char('c'), modifiers, .. })
+                        if modifiers.contains(KeyModifiers::CONTROL) => {
+                        // 原始模式下Ctrl+C不会产生信号，作为按键处理
+                        execute!(io::stderr(),
+                            MoveToColumn(0),
+                            Print(format!("\n{}\n", t(MsgKey::CtrlCExit))),
+                            MoveToColumn(0)
+                        )?;
+                        break 'session;
+                    },
+                    Event::Key(KeyEvent { code: KeyCode::Char('d'), modifiers, .. })
+                        if compose_mode && modifiers.contains(KeyModifiers::CONTROL) => {
+                        // Ctrl+D 提交编写模式的缓冲区
+                        last_esc_time = None;
+                        if !input.ends_with('\n') {
+                            execute!(io::stderr(), Print("\n"), MoveToColumn(0))?;
+                        }
+                        input.truncate(input.trim_end_matches('\n').len());
+                        reading = false;
+                    },
+                    Event::Key(KeyEvent { code, .. }) => {
+                        // 重置ESC计时器
+                        last_esc_time = None;
                         
-                        // 处理键盘事件
-                        while !selected {
-                            if poll(Duration::from_millis(100))? {
-                                match read()? {
-                                    Event::Key(KeyEvent { code: KeyCode::Up, .. }) => {
-                                        if selected_index > 0 {
-                                            selected_index -= 1;
-                                        }
-                                    },
-                                    Event::Key(KeyEvent { code: KeyCode::Down, .. }) => {
-                                        if selected_index < matches.len() - 1 {
-                                            selected_index += 1;
-                                        }
-                                    },
-                                    Event::Key(KeyEvent { code: KeyCode::Enter, .. }) => {
-                                        selected = true;
-                                    },
-                                    Event::Key(KeyEvent { code: KeyCode::Esc, .. }) => {
-                                        // 取消选择
-                                        // 恢复终端模式会通过RawModeGuard的Drop实现自动处理
-                                        execute!(io::stdout(),
-                                            MoveToColumn(0),
-                                            Print("\n已取消模型切换\n".yellow())
-                                        )?;
-                                        continue 'outer;
+                        // 处理其他按键
+                        match code {
+                            KeyCode::Char(c) => {
+                                input.push(c);
+                                eprint!("{}", c);
+                                io::stderr().flush()?;
+                            },
+                            KeyCode::Up if !compose_mode && !input_history.entries().is_empty() => {
+                                let index = match recall_index {
+                                    Some(index) => index.saturating_sub(1),
+                                    None => {
+                                        draft = input.clone();
+                                        input_history.entries().len() - 1
                                     },
-                                    _ => {}
+                                };
+                                recall_index = Some(index);
+                                replace_input(&mut input, &input_history.entries()[index], prompt.width())?;
+                            },
+                            KeyCode::Down => if let Some(index) = recall_index {
+                                if index + 1 < input_history.entries().len() {
+                                    recall_index = Some(index + 1);
+                                    replace_input(&mut input, &input_history.entries()[index + 1], prompt.width())?;
+                                } else {
+                                    recall_index = None;
+                                    replace_input(&mut input, &draft, prompt.width())?;
                                 }
-                                
-                                // 使用 crossterm 的光标控制功能移动到列表开始处
-                                execute!(io::stdout(), MoveUp(matches.len() as u16))?;
-                                
-                                // 重新显示选择
-                                for (i, model) in matches.iter().enumerate() {
-                                    // 清除整行并重新显示
-                                    execute!(io::stdout(), 
-                                        MoveToColumn(0),
-                                        Clear(ClearType::CurrentLine)
-                                    )?;
-                                    
-                                    if i == selected_index {
-                                        execute!(io::stdout(),
-                                            MoveToColumn(0),
-                                            Print(format!("{} {} ({})", ">".green(), model.name, model.provider)),
-                                            Print("\n")
-                                        )?;
-                                    } else {
-                                        execute!(io::stdout(),
-                                            MoveToColumn(0),
-                                            Print(format!("  {} ({})", model.name, model.provider)),
-                                            Print("\n")
-                                        )?;
+                            },
+                            KeyCode::Backspace => match input.pop() {
+                                Some('\n') => {
+                                    // 编写模式下删除换行，回到上一行末尾
+                                    let line = input.rsplit('\n').next().unwrap_or("");
+                                    let mut column = line.width();
+                                    if !input.contains('\n') {
+                                        column += prompt.width() + 1;
                                     }
-                                }
-                            }
+                                    execute!(io::stderr(), MoveUp(1), MoveToColumn(column as u16))?;
+                                },
+                                Some(c) => erase_char(c)?,
+                                None => {},
+                            },
+                            _ => {}
                         }
-                        
-                        // 恢复终端模式会通过RawModeGuard的Drop实现自动处理
-                        
-                        // 设置选中的模型
-                        let model = &matches[selected_index];
+                    },
+                    _ => {}
+                }
+            } else if let Some(idle) = settings.idle_clear()
+                && last_activity.elapsed() >= idle
+                && tabs.entries(&message_history, &current_model).iter().any(|(_, history, _)| !history.is_empty()) {
+                // 长时间没有输入时清空所有标签页的聊天记录，然后重新显示提示符和已输入的内容
+                message_history.clear();
+                tabs.clear_inactive();
+                execute!(io::stderr(),
+                    Print("\n"),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", tf(MsgKey::IdleCleared, &[&idle.as_secs().div_ceil(60)]).yellow())),
+                    MoveToColumn(0),
+                    Print(format!("{} ", prompt.as_str().cyan().bold())),
+                    Print(input.replace('\n', "\r\n"))
+                )?;
+            }
+        }
+        
+        // 恢复终端模式会通过RawModeGuard的Drop实现自动处理
+        
+        input_history.push(&input);
+        
+        // 编写模式下提交空白内容时不做任何处理
+        if compose_mode && input.trim().is_empty() {
+            continue;
+        }
+        
+        // `/once` 的临时系统指令，只对本轮生效
+        let mut once_instruction = None;
+        // `/image-clip` 从剪贴板读取的图片
+        let mut images = Vec::new();
+        // `@模型` 指定的只用于本轮的模型
+        let mut turn_model = None;
+        let input = match parse_command(&input) {
+            Command::Exit => break,
+            Command::Compose => {
+                compose_mode = !compose_mode;
+                let notice = if compose_mode { t(MsgKey::ComposeOn) } else { t(MsgKey::ComposeOff) };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice.yellow()))
+                )?;
+                continue;
+            },
+            Command::Clear => {
+                if !confirm_destructive(settings, t(MsgKey::ConfirmClear))? {
+                    continue;
+                }
+                // 清空聊天记录
+                message_history.clear();
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", t(MsgKey::HistoryCleared).yellow()))
+                )?;
+                continue;
+            },
+            Command::InputHistory => {
+                if input_history.entries().is_empty() {
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{}\n", t(MsgKey::InputHistoryEmpty).yellow()))
+                    )?;
+                }
+                for (index, entry) in input_history.entries().iter().enumerate() {
+                    // 多行输入只显示第一行
+                    let mut lines = entry.lines();
+                    let first = lines.next().unwrap_or_default();
+                    let more = if lines.next().is_some() { " …" } else { "" };
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{} {}{}\n", format!("#{}", index).dim(), first, more)),
+                        MoveToColumn(0)
+                    )?;
+                }
+                continue;
+            },
+            Command::HistoryClear => {
+                if !confirm_destructive(settings, t(MsgKey::ConfirmInputHistoryClear))? {
+                    continue;
+                }
+                let notice = match input_history.clear() {
+                    Ok(()) => t(MsgKey::InputHistoryCleared).to_string().yellow(),
+                    Err(e) => tf(MsgKey::InputHistoryClearFailed, &[&e]).red(),
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::Set { key, value } => {
+                let notice = match settings.set(&key, &value) {
+                    Ok(()) => tf(MsgKey::SettingUpdated, &[&key, &value]).yellow(),
+                    Err(hint) => hint.red(),
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::ShowSettings => {
+                for (key, value) in settings.settable_values() {
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("  {} = {}\n", key.cyan(), value))
+                    )?;
+                }
+                continue;
+            },
+            Command::Config => {
+                // 重新读取配置文件，与启动参数一起用于判断每项设置的来源
+                let lines = match Config::load(settings.profile.as_deref()) {
+                    Ok(config) => config_report(settings, providers, cli, &config),
+                    Err(e) => vec![tf(MsgKey::ReloadFailed, &[&format!("{:#}", e)]).red().to_string()],
+                };
+                for line in lines {
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{}\n", line))
+                    )?;
+                }
+                continue;
+            },
+            Command::ModelInfo => {
+                print_model_info(providers, &current_model)?;
+                continue;
+            },
+            Command::Headers => {
+                print_headers(providers, &current_model, settings)?;
+                continue;
+            },
+            Command::SetHeader { name, value } => {
+                let notice = match value {
+                    None => {
+                        settings.extra_headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+                        tf(MsgKey::HeaderRemoved, &[&name]).yellow()
+                    },
+                    Some(value) if !headers::is_valid(&name, &value) => {
+                        tf(MsgKey::HeaderInvalid, &[&name, &value]).red()
+                    },
+                    Some(value) => {
+                        settings.extra_headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+                        settings.extra_headers.push((name.clone(), value));
+                        tf(MsgKey::HeaderSet, &[&name]).yellow()
+                    },
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::Mode(None) => {
+                let active = settings.mode.as_deref().unwrap_or(DEFAULT_MODE);
+                let default_preset = (DEFAULT_MODE, SamplingPreset::default());
+                for (name, preset) in std::iter::once(default_preset).chain(settings.modes.iter().map(|(name, preset)| (name.as_str(), *preset))) {
+                    let marker = if name == active { "*" } else { " " };
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{} {} {}\n", marker, name.cyan(), describe_preset(&preset).dim())),
+                        MoveToColumn(0)
+                    )?;
+                }
+                continue;
+            },
+            Command::Mode(Some(name)) => {
+                let notice = match settings.set_mode(&name) {
+                    Ok(preset) => tf(MsgKey::ModeSwitched, &[&name, &describe_preset(&preset)]).yellow(),
+                    Err(message) => message.red(),
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::Save { path, tags } => {
+                let path = match path {
+                    Some(path) => path,
+                    None => default_save_path(client, providers, &current_model, &message_history, settings).await?,
+                };
+                let notice = match std::fs::write(&path, export::to_markdown(&message_history)) {
+                    Ok(()) => match sessions::record(Path::new(&path), &tags) {
+                        Ok(()) => tf(MsgKey::HistorySaved, &[&message_history.len(), &path]).yellow(),
+                        Err(e) => tf(MsgKey::SessionIndexFailed, &[&e]).red(),
+                    },
+                    Err(e) => tf(MsgKey::SaveFailed, &[&path, &e]).red(),
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::ImportChatGpt { path, index } => {
+                let imported = std::fs::read_to_string(paths::expand_home(&path))
+                    .map_err(anyhow::Error::from)
+                    .and_then(|text| import::from_chatgpt(&text, index));
+                let notice = match imported {
+                    Err(e) => tf(MsgKey::ImportFailed, &[&path, &format!("{:#}", e)]).red(),
+                    Ok(imported) if imported.messages.is_empty() => tf(MsgKey::ImportEmpty, &[&path]).red(),
+                    Ok(imported) => {
+                        if !message_history.is_empty() && !confirm_destructive(settings, t(MsgKey::ConfirmImport))? {
+                            continue;
+                        }
+                        if imported.skipped > 0 {
+                            execute!(io::stderr(),
+                                MoveToColumn(0),
+                                Print(format!("{}\n", tf(MsgKey::ImportSkipped, &[&imported.skipped]).yellow()))
+                            )?;
+                        }
+                        message_history = imported.messages;
+                        let title = imported.title.unwrap_or_default();
+                        tf(MsgKey::ImportDone, &[&message_history.len(), &title]).yellow()
+                    },
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::ExportJsonl(path) => {
+                let notice = match export::to_finetune_jsonl(&message_history, settings.system_prompt.as_deref()) {
+                    None => t(MsgKey::ExportJsonlIncomplete).to_string().red(),
+                    Some(line) => {
+                        let appended = std::fs::OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(&path)
+                            .and_then(|mut file| file.write_all(line.as_bytes()));
+                        match appended {
+                            Ok(()) => tf(MsgKey::ExportJsonlAppended, &[&message_history.len(), &path]).yellow(),
+                            Err(e) => tf(MsgKey::SaveFailed, &[&path, &e]).red(),
+                        }
+                    },
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::Role { index, role } => {
+                let notice = match message_history.get_mut(index) {
+                    None => tf(MsgKey::RoleIndexOutOfRange, &[&index, &message_history.len()]).red(),
+                    Some(_) if !MESSAGE_ROLES.contains(&role.as_str()) => tf(MsgKey::RoleInvalid, &[&role, &MESSAGE_ROLES.join("/")]).red(),
+                    Some(message) => {
+                        message.role = role.clone();
+                        tf(MsgKey::RoleChanged, &[&index, &role]).yellow()
+                    },
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::History => {
+                print_history(&message_history, settings)?;
+                continue;
+            },
+            Command::Replay => {
+                replay_last_reply(&message_history, settings, &assistant_label(settings, providers, &current_model)).await?;
+                continue;
+            },
+            Command::Buffer => {
+                settings.buffered = !settings.buffered;
+                let notice = if settings.buffered { MsgKey::BufferOn } else { MsgKey::BufferOff };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", t(notice).yellow()))
+                )?;
+                continue;
+            },
+            Command::Speed(Some(delay)) => {
+                settings.stream_delay_ms = delay;
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", tf(MsgKey::SpeedSet, &[&delay]).yellow()))
+                )?;
+                continue;
+            },
+            Command::Speed(None) => {
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", tf(MsgKey::SpeedCurrent, &[&settings.stream_delay_ms]).yellow()))
+                )?;
+                continue;
+            },
+            Command::Budget(Some(budget)) => {
+                settings.budget = (budget > 0.0).then_some(budget);
+                let notice = match settings.budget {
+                    Some(budget) => tf(MsgKey::BudgetSet, &[&format!("{:.2}", budget)]),
+                    None => t(MsgKey::BudgetCleared).to_string(),
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice.yellow()))
+                )?;
+                continue;
+            },
+            Command::Budget(None) => {
+                let spent = format!("{:.4}", cost::session_total());
+                let notice = match settings.budget {
+                    Some(budget) => tf(MsgKey::BudgetCurrent, &[&spent, &format!("{:.2}", budget)]),
+                    None => tf(MsgKey::BudgetNone, &[&spent]),
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice.yellow()))
+                )?;
+                continue;
+            },
+            Command::Reload => {
+                match Config::load(settings.profile.as_deref()) {
+                    Ok(config) => {
+                        settings.apply_config(&config);
+                        *providers = load_providers(&config);
+                        let model_count: usize = providers.iter().map(|p| p.models.len()).sum();
+                        execute!(io::stderr(),
+                            MoveToColumn(0),
+                            Print(format!("{}\n", tf(MsgKey::Reloaded, &[&providers.len(), &model_count]).yellow()))
+                        )?;
+                        if get_provider_by_model(providers, &current_model).is_none() {
+                            execute!(io::stderr(),
+                                MoveToColumn(0),
+                                Print(format!("{}\n", tf(MsgKey::CurrentModelMissing, &[&current_model]).red()))
+                            )?;
+                        }
+                    },
+                    Err(e) => {
+                        // 配置有误时保留原来的提供商列表
+                        execute!(io::stderr(),
+                            MoveToColumn(0),
+                            Print(format!("{}\n", tf(MsgKey::ReloadFailed, &[&format!("{:#}", e)]).red()))
+                        )?;
+                    }
+                }
+                continue;
+            },
+            Command::Prefill(prefill) => {
+                let notice = match &prefill {
+                    Some(text) => tf(MsgKey::PrefillSet, &[text]),
+                    None => t(MsgKey::PrefillCleared).to_string(),
+                };
+                pending_prefill = prefill;
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice.yellow()))
+                )?;
+                continue;
+            },
+            Command::Models(query) => {
+                // 保存列表，之后 `/model <序号>` 按同样的顺序选择
+                model_listing = find_models(providers, &query);
+                if model_listing.is_empty() {
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{}\n", t(MsgKey::ModelListEmpty).yellow()))
+                    )?;
+                }
+                let width = model_listing.len().to_string().len();
+                for (i, model) in model_listing.iter().enumerate() {
+                    let marker = if model.id == current_model { "*" } else { " " };
+                    let star = if model.favorite { "★ " } else { "" };
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{} {:>width$}. {}{} {}\n", marker, i + 1, star, model.name, format!("({})", model.provider).dim())),
+                        MoveToColumn(0)
+                    )?;
+                }
+                continue;
+            },
+            Command::Pin(text) => {
+                settings.pins.push(text);
+                let notice = match pins::save(&settings.pins) {
+                    Ok(()) => tf(MsgKey::PinAdded, &[&settings.pins.len()]).yellow(),
+                    Err(e) => tf(MsgKey::PinSaveFailed, &[&e]).red(),
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::Unpin(index) => {
+                let notice = if index > settings.pins.len() {
+                    tf(MsgKey::PinIndexOutOfRange, &[&index, &settings.pins.len()]).red()
+                } else {
+                    let pin = settings.pins.remove(index - 1);
+                    match pins::save(&settings.pins) {
+                        Ok(()) => tf(MsgKey::PinRemoved, &[&pin]).yellow(),
+                        Err(e) => tf(MsgKey::PinSaveFailed, &[&e]).red(),
+                    }
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::Pins => {
+                if settings.pins.is_empty() {
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{}\n", t(MsgKey::PinsEmpty).yellow()))
+                    )?;
+                }
+                for (index, pin) in settings.pins.iter().enumerate() {
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("  {} {}\n", format!("{}.", index + 1).cyan(), pin))
+                    )?;
+                }
+                continue;
+            },
+            Command::TabNew(name) => {
+                let index = tabs.open(name, &mut message_history, &mut current_model);
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", tf(MsgKey::TabCreated, &[&(index + 1), &tabs.active().1]).green()))
+                )?;
+                continue;
+            },
+            Command::TabList => {
+                let (active, _) = tabs.active();
+                for (index, (name, history, model)) in tabs.entries(&message_history, &current_model).into_iter().enumerate() {
+                    let marker = if index == active { "*" } else { " " };
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{} {} {}  {}\n",
+                            marker.green().bold(),
+                            format!("{}.", index + 1).cyan(),
+                            name,
+                            tf(MsgKey::TabListEntry, &[&history.len(), &model]).dim()))
+                    )?;
+                }
+                continue;
+            },
+            Command::TabSwitch(index) => {
+                let notice = if tabs.switch(index - 1, &mut message_history, &mut current_model) {
+                    tf(MsgKey::TabSwitched, &[&index, &tabs.active().1, &message_history.len(), &current_model]).green()
+                } else {
+                    tf(MsgKey::TabIndexOutOfRange, &[&index, &tabs.entries(&message_history, &current_model).len()]).red()
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::SelectModel(index) => {
+                let notice = if model_listing.is_empty() {
+                    t(MsgKey::NoModelListing).to_string().red()
+                } else {
+                    match model_listing.get(index - 1) {
+                        Some(model) => {
+                            current_model = model.id.clone();
+                            tf(MsgKey::ModelSwitched, &[&model.name]).green()
+                        },
+                        None => tf(MsgKey::ModelIndexOutOfRange, &[&index, &model_listing.len()]).red(),
+                    }
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::Favorite => {
+                let name = providers.iter()
+                    .flat_map(|p| &p.models)
+                    .find(|m| m.id == current_model)
+                    .map_or_else(|| current_model.clone(), |m| m.name.clone());
+                let notice = match toggle_favorite(providers, &current_model) {
+                    Ok(true) => tf(MsgKey::FavoriteAdded, &[&name]).yellow(),
+                    Ok(false) => tf(MsgKey::FavoriteRemoved, &[&name]).yellow(),
+                    Err(e) => tf(MsgKey::FavoriteSaveFailed, &[&e]).red(),
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::Provider(None) => {
+                let current_provider = get_provider_by_model(providers, &current_model).map(|p| p.name.as_str());
+                for provider in providers.iter() {
+                    let marker = if Some(provider.name.as_str()) == current_provider { "*" } else { " " };
+                    let model = provider.default_model().map_or("-", |m| m.name.as_str());
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{} {} {}\n", marker, provider.name, format!("({})", model).dim())),
+                        MoveToColumn(0)
+                    )?;
+                }
+                continue;
+            },
+            Command::Provider(Some(name)) => {
+                let notice = match providers.iter().find(|p| p.name == name) {
+                    None => tf(MsgKey::ProviderNotFound, &[&name]).red(),
+                    Some(provider) => match provider.default_model() {
+                        None => tf(MsgKey::ProviderNoModels, &[&name]).red(),
+                        Some(model) => {
+                            current_model = model.id.clone();
+                            tf(MsgKey::ModelSwitched, &[&model.name]).green()
+                        },
+                    },
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", notice))
+                )?;
+                continue;
+            },
+            Command::Compact => {
+                compact_history(client, providers, &current_model, &mut message_history, settings).await?;
+                continue;
+            },
+            Command::Continue => {
+                continue_reply(client, providers, &current_model, &mut message_history, settings).await?;
+                continue;
+            },
+            Command::Once { instruction, message } => {
+                once_instruction = Some(instruction);
+                message
+            },
+            Command::ImageClip(question) => match clipboard::read_image() {
+                Ok(Some(image)) => {
+                    images.push(image);
+                    question
+                },
+                result => {
+                    let notice = match result {
+                        Err(e) => tf(MsgKey::ClipboardFailed, &[&format!("{:#}", e)]).red(),
+                        _ => t(MsgKey::ClipboardNoImage).to_string().yellow(),
+                    };
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{}\n", notice))
+                    )?;
+                    continue;
+                },
+            },
+            Command::File { path, question } => match build_file_prompt(&path, &question)? {
+                Some(prompt) => prompt,
+                None => continue,
+            },
+            Command::Invalid(hint) => {
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", hint.red()))
+                )?;
+                continue;
+            },
+            Command::ModelOverride { query, message } => {
+                let matches = find_models(providers, &query);
+                if matches.is_empty() {
+                    execute!(io::stderr(),
+                        MoveToColumn(0),
+                        Print(format!("{}\n", tf(MsgKey::ModelOverrideNotFound, &[&query]).red()))
+                    )?;
+                    continue;
+                }
+                let Some(model) = select_model(&matches)? else {
+                    continue;
+                };
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", tf(MsgKey::ModelOverride, &[&model.name]).dim()))
+                )?;
+                turn_model = Some(model.id);
+                message
+            },
+            Command::Message(message) => {
+                // 先尝试查找匹配的模型
+                let matches = find_models(providers, &message);
+                if !matches.is_empty() {
+                    if let Some(model) = select_model(&matches)? {
                         current_model = model.id.clone();
-                        execute!(io::stdout(),
+                        execute!(io::stderr(),
                             MoveToColumn(0),
-                            SetForegroundColor(Color::Green),
-                            Print("已切换到模型: "),
-                            Print(model.name.clone()),
+                            SetColor(Color::Green),
+                            Print(tf(MsgKey::ModelSwitched, &[&model.name])),
                             Print("\n"),
-                            ResetColor
+                            Reset
                         )?;
-                        continue;
                     }
+                    continue;
                 }
+                message
+            },
+        };
+        
+        // 误粘贴超长内容时先确认，避免悄悄发出昂贵的请求
+        if let Some(limit) = settings.input_warn_chars {
+            let chars = input.chars().count();
+            if chars > limit && !confirm_destructive(settings, &tf(MsgKey::LongInputConfirm, &[&chars, &limit]))? {
+                execute!(io::stderr(),
+                    MoveToColumn(0),
+                    Print(format!("{}\n", t(MsgKey::SendCancelled).yellow()))
+                )?;
+                continue;
+            }
+        }
+        
+        // 累计费用超过上限后，每次发送前都需要确认
+        if let Some(budget) = settings.budget
+            && cost::session_total() >= budget {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", tf(MsgKey::BudgetExceeded, &[&format!("{:.4}", cost::session_total()), &format!("{:.2}", budget)]).red()))
+            )?;
+            if !confirm(t(MsgKey::BudgetConfirm))? {
+                continue;
+            }
+        }
+        
+        // 如果没有匹配的模型，则视为普通消息
+        let model_id = turn_model.as_deref().unwrap_or(&current_model);
+        if let Some(threshold) = settings.auto_compact_tokens {
+            let tokens = message_history.iter().map(|message| estimate_tokens(&message.content)).sum::<usize>() + estimate_tokens(&input);
+            if tokens > threshold {
+                auto_compact(client, providers, model_id, &mut message_history, settings, tokens, threshold).await?;
+            }
+        }
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{} ", assistant_label(settings, providers, model_id).green().bold()))
+        )?;
+        io::stderr().flush()?;
+        
+        let options = TurnOptions {
+            prefill: pending_prefill.as_deref(),
+            ephemeral_system: once_instruction.as_deref(),
+            images: &images,
+            ..TurnOptions::default()
+        };
+        match send_with_hooks(client, providers, &input, model_id, &mut message_history, settings, options).await {
+            Ok(response) => {
+                // 回复超出一屏时进入分页器，方便回看
+                if settings.pager && io::stdout().is_terminal() && pager::exceeds_screen(&response) {
+                    pager::page(&response)?;
+                }
+            },
+            Err(e) => report_chat_error(e)?,
+        }
+        pending_prefill = None;
+    }
+    
+    let histories: Vec<&[ChatMessage]> = tabs.entries(&message_history, &current_model)
+        .into_iter()
+        .map(|(_, history, _)| history)
+        .collect();
+    shutdown(&current_model, &histories, settings)
+}
+
+/// 退出交互模式时的清理，`exit`、连续两次ESC和Ctrl+C都经过这里
+/// 保存当前模型供下次启动时使用，设置了 `HAMBUR_AUTOSAVE` 时自动保存每个标签页的聊天记录，最后显示会话费用
+/// 输入历史在每次输入后已经追加到文件中，不需要在这里保存
+fn shutdown(current_model: &str, histories: &[&[ChatMessage]], settings: &Settings) -> Result<()> {
+    // 写入失败只影响下次启动时的默认模型，不打断退出
+    let _ = write_last_model(current_model);
+    
+    let Some(dir) = &settings.autosave_dir else {
+        return print_session_cost();
+    };
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    for (index, message_history) in histories.iter().enumerate() {
+        if message_history.is_empty() {
+            continue;
+        }
+        // 只有一个标签页时保持原来的文件名，多个标签页时附加标签页序号
+        let name = if histories.len() == 1 {
+            format!("{}.md", timestamp)
+        } else {
+            format!("{}-{}.md", timestamp, index + 1)
+        };
+        let path = dir.join(name);
+        let saved = std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&path, export::to_markdown(message_history)));
+        let notice = match saved {
+            Ok(()) => {
+                // 索引只用于查询，更新失败不影响已保存的文件
+                let _ = sessions::record(&path, &[]);
+                tf(MsgKey::AutosaveDone, &[&path.display()]).dim()
+            },
+            Err(e) => tf(MsgKey::AutosaveFailed, &[&path.display(), &e]).red(),
+        };
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", notice)),
+            MoveToColumn(0)
+        )?;
+    }
+    
+    print_session_cost()
+}
+
+/// 续写聊天记录中最后一条assistant回复，续写的内容追加到该回复中，不产生新的一轮对话
+/// 提供商支持预填充时重新发送原问题并以已有回复作为预填充，否则附加一条临时的“继续”指令，
+/// 完成后再把指令和续写的内容合并回原来的回复
+async fn continue_reply(client: &reqwest::Client, providers: &[ModelProvider], model_id: &str, message_history: &mut Vec<ChatMessage>, settings: &Settings) -> Result<()> {
+    if message_history.last().is_none_or(|message| message.role != "assistant") {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", t(MsgKey::ContinueNothing).red()))
+        )?;
+        return Ok(());
+    }
+    
+    execute!(io::stderr(),
+        MoveToColumn(0),
+        Print(format!("{} ", assistant_label(settings, providers, model_id).green().bold()))
+    )?;
+    io::stderr().flush()?;
+    
+    let supports_prefill = get_provider_by_model(providers, model_id).is_some_and(|provider| provider.supports_prefill);
+    let len = message_history.len();
+    let result = if supports_prefill && len >= 2 && message_history[len - 2].role == "user" {
+        let partial = message_history.pop().context(t(MsgKey::ChatHistoryEmpty))?;
+        let question = message_history.pop().context(t(MsgKey::ChatHistoryEmpty))?;
+        let options = TurnOptions {
+            prefill: Some(&partial.content),
+            continuation: true,
+            ..TurnOptions::default()
+        };
+        let result = send_chat_request(client, providers, &question.content, model_id, message_history, settings, options).await;
+        if message_history.len() == len {
+            // 保留原来的消息时间
+            message_history[len - 2].timestamp = question.timestamp;
+            message_history[len - 1].timestamp = partial.timestamp;
+        } else {
+            // 请求失败时恢复原来的问题和回复
+            message_history.push(question);
+            message_history.push(partial);
+        }
+        result
+    } else {
+        let result = send_chat_request(client, providers, t(MsgKey::ContinueInstruction), model_id, message_history, settings, TurnOptions::default()).await;
+        if message_history.len() == len + 2 {
+            let continued = message_history.pop().context(t(MsgKey::ChatHistoryEmpty))?;
+            message_history.pop();
+            if let Some(reply) = message_history.last_mut() {
+                reply.content.push_str(&continued.content);
             }
-            
-            // 如果没有匹配的模型，则视为普通消息
-            execute!(io::stdout(),
+        }
+        result
+    };
+    
+    if let Err(e) = result {
+        report_chat_error(e)?;
+    }
+    Ok(())
+}
+
+/// 不带路径的 `/save` 使用的文件名，保存在当前目录中
+/// 开启 `HAMBUR_AUTOTITLE` 时请求当前模型为对话生成标题并转换为文件名，请求失败或与已有文件重名时使用当前时间
+async fn default_save_path(client: &reqwest::Client, providers: &[ModelProvider], model_id: &str, message_history: &[ChatMessage], settings: &Settings) -> Result<String> {
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    if !settings.autotitle || message_history.is_empty() {
+        return Ok(format!("{}.md", timestamp));
+    }
+
+    execute!(io::stderr(), MoveToColumn(0), Print(format!("{}\n", t(MsgKey::TitleGenerating).dim())))?;
+    let mut messages = message_history.to_vec();
+    messages.push(ChatMessage::new("user", t(MsgKey::TitleInstruction)));
+    let slug = match fetch_reply(client, providers, model_id, messages, settings).await {
+        Ok(title) => export::slugify(title.lines().next().unwrap_or_default().trim_matches(['"', '“', '”', '#', '*', ' '])),
+        Err(e) => {
+            execute!(io::stderr(),
                 MoveToColumn(0),
-                Print(format!("{} ", "AI:".green().bold()))
+                Print(format!("{}\n", tf(MsgKey::TitleFailed, &[&format!("{:#}", e)]).yellow()))
             )?;
-            io::stdout().flush()?;
-            
-            send_chat_request(client, input, &current_model, &mut message_history).await?;
+            String::new()
+        },
+    };
+    let path = match slug.as_str() {
+        "" => format!("{}.md", timestamp),
+        slug if Path::new(&format!("{}.md", slug)).exists() => format!("{}-{}.md", slug, timestamp),
+        slug => format!("{}.md", slug),
+    };
+    Ok(path)
+}
+
+/// 让当前模型总结最近一轮对话之前的聊天记录，确认后用一条包含摘要的系统消息代替这些消息，
+/// 最近一轮对话原样保留；摘要在生成时直接显示，方便确认前检查
+async fn compact_history(client: &reqwest::Client, providers: &[ModelProvider], model_id: &str, message_history: &mut Vec<ChatMessage>, settings: &Settings) -> Result<()> {
+    let keep_from = message_history.iter().rposition(|message| message.role == "user").unwrap_or(0);
+    if keep_from == 0 {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", t(MsgKey::CompactNothing).yellow()))
+        )?;
+        return Ok(());
+    }
+    
+    execute!(io::stderr(),
+        MoveToColumn(0),
+        Print(format!("{} ", assistant_label(settings, providers, model_id).green().bold()))
+    )?;
+    io::stderr().flush()?;
+    
+    // 总结请求使用聊天记录的副本，不影响原来的记录
+    let mut scratch = message_history[..keep_from].to_vec();
+    let summary = match send_chat_request(client, providers, t(MsgKey::CompactInstruction), model_id, &mut scratch, settings, TurnOptions::default()).await {
+        Ok(summary) if !summary.trim().is_empty() => summary,
+        Ok(_) => return Ok(()),
+        Err(e) => return report_chat_error(e),
+    };
+    
+    if !confirm_destructive(settings, &tf(MsgKey::CompactConfirm, &[&keep_from]))? {
+        return Ok(());
+    }
+    replace_with_summary(message_history, keep_from, &summary)
+}
+
+/// 估算的上下文超过 `HAMBUR_AUTO_COMPACT_TOKENS` 时在发送前调用，与 `/compact` 一样保留最近一轮对话，
+/// 但摘要在后台生成，不显示也不需要确认；较早的部分只剩之前的摘要时不再压缩，失败时只提示，消息照常发送
+async fn auto_compact(client: &reqwest::Client, providers: &[ModelProvider], model_id: &str, message_history: &mut Vec<ChatMessage>, settings: &Settings, tokens: usize, threshold: usize) -> Result<()> {
+    let keep_from = message_history.iter().rposition(|message| message.role == "user").unwrap_or(0);
+    if !message_history[..keep_from].iter().any(|message| message.role == "user") {
+        return Ok(());
+    }
+    
+    execute!(io::stderr(),
+        MoveToColumn(0),
+        Print(format!("{}\n", tf(MsgKey::AutoCompacting, &[&tokens, &threshold]).dim()))
+    )?;
+    let mut messages = message_history[..keep_from].to_vec();
+    messages.push(ChatMessage::new("user", t(MsgKey::CompactInstruction)));
+    match fetch_reply(client, providers, model_id, messages, settings).await {
+        Ok(summary) if !summary.trim().is_empty() => replace_with_summary(message_history, keep_from, &summary),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", tf(MsgKey::AutoCompactFailed, &[&e]).red()))
+            )?;
+            Ok(())
+        },
+    }
+}
+
+/// 用一条包含摘要的系统消息代替 `keep_from` 之前的聊天记录
+fn replace_with_summary(message_history: &mut Vec<ChatMessage>, keep_from: usize, summary: &str) -> Result<()> {
+    let recent = message_history.split_off(keep_from);
+    *message_history = vec![ChatMessage::new("system", tf(MsgKey::CompactSummary, &[&summary.trim()]))];
+    message_history.extend(recent);
+    execute!(io::stderr(),
+        MoveToColumn(0),
+        Print(format!("{}\n", tf(MsgKey::CompactDone, &[&keep_from]).yellow()))
+    )?;
+    Ok(())
+}
+
+/// 采样预设的参数说明，例如 `temperature=0.7, top_p=0.9`
+fn describe_preset(preset: &SamplingPreset) -> String {
+    let values: Vec<String> = [("temperature", preset.temperature), ("top_p", preset.top_p)]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| format!("{}={}", name, value)))
+        .collect();
+    if values.is_empty() {
+        t(MsgKey::ProviderDefaults).to_string()
+    } else {
+        values.join(", ")
+    }
+}
+
+/// 退出时显示本次会话累计的估算费用，没有费用记录时不显示
+fn print_session_cost() -> Result<()> {
+    let total = cost::session_total();
+    if total > 0.0 {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", tf(MsgKey::SessionCost, &[&format!("{:.4}", total)]).dim())),
+            MoveToColumn(0)
+        )?;
+    }
+    Ok(())
+}
+
+/// 从匹配的模型中选择一个
+/// 只有一个匹配时直接返回，多个匹配时使用上下方向键选择，按ESC取消时返回None
+/// 在菜单中输入的字符作为筛选条件，只显示名称或ID包含该条件的模型，退格放宽筛选
+fn select_model(matches: &[Model]) -> Result<Option<Model>> {
+    if matches.len() == 1 {
+        return Ok(Some(matches[0].clone()));
+    }
+    
+    // 多个匹配，在备用屏幕中使用上下方向键选择，结束后恢复原有的终端内容
+    let selection = {
+        // 进入备用屏幕并启用原始模式以捕获键盘事件
+        let _screen_guard = FullScreenGuard::enter()?;
+
+        let mut selected_index = 0;
+        let mut filter = String::new();
+        loop {
+            let query = filter.to_lowercase();
+            let visible: Vec<&Model> = matches.iter()
+                .filter(|m| matches_menu_filter(m, &query))
+                .collect();
+            draw_model_menu(&visible, selected_index, &filter)?;
+
+            match read()? {
+                Event::Key(KeyEvent { code: KeyCode::Up, .. }) => {
+                    selected_index = selected_index.saturating_sub(1);
+                },
+                Event::Key(KeyEvent { code: KeyCode::Down, .. }) if selected_index + 1 < visible.len() => {
+                    selected_index += 1;
+                },
+                Event::Key(KeyEvent { code: KeyCode::Enter, .. }) if !visible.is_empty() => {
+                    break Some(visible[selected_index].clone());
+                },
+                Event::Key(KeyEvent { code: KeyCode::Esc, .. }) => break None,
+                Event::Key(KeyEvent { code: KeyCode::Backspace, .. }) => {
+                    filter.pop();
+                    selected_index = 0;
+                },
+                Event::Key(KeyEvent { code: KeyCode::Char(c), modifiers, .. }) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                    filter.push(c);
+                    selected_index = 0;
+                },
+                _ => {}
+            }
+        }
+        // 恢复终端模式和主屏幕会通过守卫的Drop实现自动处理
+    };
+
+    match selection {
+        Some(model) => Ok(Some(model)),
+        None => {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", t(MsgKey::ModelSwitchCancelled).yellow()))
+            )?;
+            Ok(None)
+        }
+    }
+}
+
+/// 模型名称或ID是否包含菜单中输入的筛选条件，忽略大小写，`query` 需要已转换为小写
+fn matches_menu_filter(model: &Model, query: &str) -> bool {
+    model.name.to_lowercase().contains(query) || model.id.to_lowercase().contains(query)
+}
+
+/// 在备用屏幕上绘制模型选择菜单，第二行显示当前的筛选条件
+fn draw_model_menu(matches: &[&Model], selected_index: usize, filter: &str) -> Result<()> {
+    let mut stderr = io::stderr();
+    queue!(stderr,
+        MoveTo(0, 0),
+        Clear(ClearType::All),
+        Print(t(MsgKey::MultipleModels).yellow()),
+        MoveTo(0, 1),
+        Print(tf(MsgKey::MenuFilter, &[&filter]).dim())
+    )?;
+    if matches.is_empty() {
+        queue!(stderr, MoveTo(0, 2), Print(t(MsgKey::MenuNoMatch).red()))?;
+    }
+
+    // 匹配的模型超过一屏时只显示选中项附近的一段
+    let (_, rows) = size()?;
+    let visible = menu_window(matches.len(), selected_index, usize::from(rows).saturating_sub(2));
+    for (row, i) in visible.enumerate() {
+        let model = matches[i];
+        queue!(stderr, MoveTo(0, row as u16 + 2))?;
+        let star = if model.favorite { "★ " } else { "" };
+        if i == selected_index {
+            queue!(stderr, Print(format!("{} {}{} ({})", ">".green(), star, model.name, model.provider)))?;
+        } else {
+            queue!(stderr, Print(format!("  {}{} ({})", star, model.name, model.provider)))?;
+        }
+    }
+
+    stderr.flush()?;
+    Ok(())
+}
+
+/// 菜单中显示的模型序号范围：共 `len` 项、可用 `rows` 行时，让选中项尽量位于中间，且不越过列表两端
+fn menu_window(len: usize, selected: usize, rows: usize) -> std::ops::Range<usize> {
+    let rows = rows.max(1);
+    if len <= rows {
+        return 0..len;
+    }
+    let start = selected.saturating_sub(rows / 2).min(len - rows);
+    start..start + rows
+}
+
+/// 在输入回显中擦除一个字符，按字符的显示宽度（中文等宽字符占两列）退格
+/// 光标位于行首时说明输入已折行，先回到上一行末尾再擦除
+fn erase_char(c: char) -> Result<()> {
+    let width = c.width().unwrap_or(0) as u16;
+    if width == 0 {
+        return Ok(());
+    }
+
+    let mut stderr = io::stderr();
+    let (column, _) = position()?;
+    if column == 0 {
+        let (columns, _) = size()?;
+        let start = columns.saturating_sub(width);
+        execute!(stderr,
+            MoveUp(1),
+            MoveToColumn(start),
+            Print(" ".repeat(width as usize)),
+            MoveToColumn(start)
+        )?;
+    } else {
+        let erase = "\u{8} \u{8}".repeat(width as usize);
+        execute!(stderr, Print(erase))?;
+    }
+    stderr.flush()?;
+    Ok(())
+}
+
+/// 用找回的历史记录替换输入提示符后的内容，`prompt_width` 为输入提示符的显示宽度
+fn replace_input(input: &mut String, replacement: &str, prompt_width: usize) -> Result<()> {
+    let lines = input.matches('\n').count();
+    if lines > 0 {
+        // 多行内容回到第一行提示符之后，清除其后的所有内容
+        execute!(io::stderr(),
+            MoveUp(lines as u16),
+            MoveToColumn(prompt_width as u16 + 1),
+            Clear(ClearType::FromCursorDown)
+        )?;
+    } else {
+        for c in input.chars().rev() {
+            erase_char(c)?;
+        }
+    }
+    *input = replacement.to_string();
+    execute!(io::stderr(), Print(replacement.replace('\n', "\r\n")))?;
+    Ok(())
+}
+
+/// 当前生效的设置及其来源、颜色输出状态和已启用的提供商，每项一行
+/// 不带颜色，`--show-config` 的输出可以直接重定向到文件；提供商只显示密钥是否可用及其来源，不显示密钥本身
+fn config_report(settings: &Settings, providers: &[ModelProvider], cli: &Cli, config: &Config) -> Vec<String> {
+    let mut lines: Vec<String> = settings.effective_config(cli, config)
+        .into_iter()
+        .map(|(key, value, source)| format!("  {} = {}  [{}]", key, value, source.label()))
+        .collect();
+    let color_source = if color::enabled() { Source::Default } else { Source::Env };
+    lines.push(format!("  color = {}  [{}]", color::enabled(), color_source.label()));
+
+    lines.push(t(MsgKey::ConfigProviders).to_string());
+    for provider in providers {
+        // 密钥可能来自钥匙串、密钥文件或环境变量，查找顺序与发送请求时相同
+        let key_state = match secrets::resolve_api_key_with_source(provider) {
+            Ok((key, source)) if !key.is_empty() => format!("{} [{}]", t(MsgKey::ApiKeySet), source.label()),
+            _ => format!("{} [{}]", t(MsgKey::ApiKeyMissing), provider.api_key_env),
+        };
+        lines.push(format!("  {}  {}  key={}  {}",
+            provider.name,
+            provider.api_base,
+            key_state,
+            tf(MsgKey::ModelCount, &[&provider.models.len()])));
+    }
+    lines
+}
+
+/// 输入提示符，设置了 `HAMBUR_PROMPT_FORMAT` 时将其中的 `{model}` 替换为当前模型的名称，
+/// `{count}` 替换为聊天记录中的消息数
+fn input_prompt(settings: &Settings, providers: &[ModelProvider], model_id: &str, count: usize) -> String {
+    let Some(format) = &settings.prompt_format else {
+        return settings.user_label().to_string();
+    };
+    let name = providers.iter()
+        .flat_map(|p| &p.models)
+        .find(|m| m.id == model_id)
+        .map_or(model_id, |m| m.name.as_str());
+    format.replace("{model}", name).replace("{count}", &count.to_string())
+}
+
+/// 回复前显示的标签，开启 `--show-model-label` 时显示当前模型的名称
+fn assistant_label(settings: &Settings, providers: &[ModelProvider], model_id: &str) -> String {
+    if settings.show_model_label {
+        let name = providers.iter()
+            .flat_map(|p| &p.models)
+            .find(|m| m.id == model_id)
+            .map_or(model_id, |m| m.name.as_str());
+        return format!("{}:", name);
+    }
+    settings.assistant_label().to_string()
+}
+
+/// 打印当前提供商实际发送的请求头，认证信息只显示开头和结尾
+fn print_headers(providers: &[ModelProvider], model_id: &str, settings: &Settings) -> Result<()> {
+    let Some(provider) = get_provider_by_model(providers, model_id) else {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", tf(MsgKey::CurrentModelMissing, &[&model_id]).red()))
+        )?;
+        return Ok(());
+    };
+    let built = resolve_api_key(provider).and_then(|api_key| {
+        let mut headers = headers::build_headers(&api_key, provider)?;
+        headers::insert_extra(&mut headers, &settings.extra_headers)?;
+        Ok(headers)
+    });
+    let headers = match built {
+        Ok(headers) => headers,
+        Err(e) => {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", format!("{:#}", e).red()))
+            )?;
+            return Ok(());
+        },
+    };
+    
+    for (name, value) in &headers {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("  {}: {}\n", name.as_str().cyan(), headers::display_value(name, value)))
+        )?;
+    }
+    Ok(())
+}
+
+/// 显示当前模型的ID、名称、提供商、API地址以及保存密钥的环境变量（不显示密钥本身）
+fn print_model_info(providers: &[ModelProvider], model_id: &str) -> Result<()> {
+    let Some(provider) = get_provider_by_model(providers, model_id) else {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", tf(MsgKey::CurrentModelMissing, &[&model_id]).red()))
+        )?;
+        return Ok(());
+    };
+    let model = provider.models.iter().find(|m| m.id == model_id);
+    let key_status = if resolve_api_key(provider).is_ok() {
+        t(MsgKey::KeySet).green()
+    } else {
+        t(MsgKey::KeyUnset).red()
+    };
+
+    let rows = [
+        (t(MsgKey::InfoId), model_id.to_string()),
+        (t(MsgKey::InfoName), model.map(|m| m.name.clone()).unwrap_or_default()),
+        (t(MsgKey::InfoProvider), provider.name.clone()),
+        (t(MsgKey::InfoApiBase), provider.api_base.clone()),
+        (t(MsgKey::InfoKeyEnv), format!("{} ({})", provider.api_key_env, key_status)),
+    ];
+
+    execute!(io::stderr(),
+        MoveToColumn(0),
+        Print(format!("{}\n", t(MsgKey::ModelInfoTitle).yellow().bold()))
+    )?;
+    for (label, value) in rows {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("  {} {}\n", label.cyan(), value))
+        )?;
+    }
+    Ok(())
+}
+
+/// 打印当前会话的聊天记录，每条消息前显示序号（供 `/role` 使用），开启时间戳时显示本地时间
+fn print_history(message_history: &[ChatMessage], settings: &Settings) -> Result<()> {
+    if message_history.is_empty() {
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{}\n", t(MsgKey::HistoryEmpty).yellow()))
+        )?;
+        return Ok(());
+    }
+
+    for (index, message) in message_history.iter().enumerate() {
+        let label = match message.role.as_str() {
+            "user" => settings.user_label().cyan().bold(),
+            "assistant" => settings.assistant_label().green().bold(),
+            role => role.bold(),
+        };
+        let time = match message.timestamp {
+            Some(time) if settings.timestamps => format!("{} ", format!("[{}]", time.format("%H:%M:%S")).dim()),
+            _ => String::new(),
+        };
+        execute!(io::stderr(),
+            MoveToColumn(0),
+            Print(format!("{} {}{} {}\n", format!("#{}", index).dim(), time, label, message.content.replace('\n', "\r\n")))
+        )?;
+    }
+    Ok(())
+}
+
+/// 在执行清空记录等无法撤销的操作前请求确认
+/// 设置了 `HAMBUR_NO_CONFIRM` 时直接返回true
+fn confirm_destructive(settings: &Settings, prompt: &str) -> Result<bool> {
+    if !settings.confirm_destructive {
+        return Ok(true);
+    }
+    confirm(prompt)
+}
+
+/// 超过该大小（字节）的文件在附加前需要确认
+const LARGE_FILE_BYTES: u64 = 100 * 1024;
+
+/// 读取文件并将其内容放入代码块，拼接在问题之前
+/// 文件不存在或用户取消时打印提示并返回None
+fn build_file_prompt(path: &str, question: &str) -> Result<Option<String>> {
+    let size = match std::fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(e) => {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", tf(MsgKey::FileReadFailed, &[&path, &e]).red()))
+            )?;
+            return Ok(None);
+        }
+    };
+    
+    if size > LARGE_FILE_BYTES {
+        let prompt = tf(MsgKey::LargeFileConfirm, &[&path, &(size / 1024)]);
+        if !confirm(&prompt)? {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", t(MsgKey::FileCancelled).yellow()))
+            )?;
+            return Ok(None);
         }
     }
     
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            execute!(io::stderr(),
+                MoveToColumn(0),
+                Print(format!("{}\n", tf(MsgKey::FileReadFailed, &[&path, &e]).red()))
+            )?;
+            return Ok(None);
+        }
+    };
+    
+    Ok(Some(fence_context(path, &contents, question)))
+}
+
+/// 将上下文内容放入代码块（`info` 作为代码块的信息字符串），拼接在问题之前
+fn fence_context(info: &str, contents: &str, question: &str) -> String {
+    let newline = if contents.ends_with('\n') { "" } else { "\n" };
+    let block = format!("```{}\n{}{}```", info, contents, newline);
+    if question.is_empty() {
+        block
+    } else {
+        format!("{}\n\n{}", block, question)
+    }
+}
+
+/// 构造单次模式的问题
+/// 使用 `--prompt-file` 时读取文件内容作为问题；
+/// 使用 `--ask` 且 stdin 被重定向时，读取全部 stdin 作为上下文放入代码块，`--ask` 的内容作为指令
+fn one_shot_prompt(cli: &Cli) -> Result<Option<String>> {
+    if let Some(path) = &cli.prompt_file {
+        let prompt = std::fs::read_to_string(path).with_context(|| tf(MsgKey::PromptFileReadFailed, &[path]))?;
+        return Ok(Some(prompt.trim_end().to_string()));
+    }
+    if let Some(instruction) = &cli.ask {
+        if io::stdin().is_terminal() {
+            return Ok(Some(instruction.clone()));
+        }
+        let mut context = String::new();
+        io::stdin().read_to_string(&mut context).context(t(MsgKey::StdinReadFailed))?;
+        return Ok(Some(fence_context("", &context, instruction)));
+    }
+    Ok(cli.prompt.clone())
+}
+
+/// 等待服务就绪的最长时间
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 反复请求提供商的就绪检查地址，直到能够建立连接或超时
+/// 收到任何HTTP响应（包括错误状态码）都说明服务已经启动，超时后只提示，之后的请求照常报错
+async fn wait_for_ready(client: &reqwest::Client, provider: &ModelProvider) {
+    let url = models::health_url(provider);
+    let start = std::time::Instant::now();
+    let mut backoff = Duration::from_millis(250);
+    let mut notified = false;
+
+    while client.get(&url).send().await.is_err() {
+        if start.elapsed() >= READY_TIMEOUT {
+            eprintln!("{}", tf(MsgKey::ReadyTimeout, &[&provider.name, &READY_TIMEOUT.as_secs()]).red());
+            return;
+        }
+        if !notified {
+            eprintln!("{}", tf(MsgKey::WaitingForReady, &[&provider.name]).dim());
+            notified = true;
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(Duration::from_secs(4));
+    }
+}
+
+/// 从各提供商的 `/models` 接口获取模型列表并写入缓存
+/// 单个提供商失败时只打印警告，不影响其他提供商
+async fn refresh_models(client: &reqwest::Client, providers: &[ModelProvider]) -> Result<()> {
+    for provider in providers {
+        match fetch_models(client, provider).await {
+            Ok(models) => {
+                write_models_cache(&provider.name, &models)?;
+                eprintln!("{}", tf(MsgKey::ModelsFetched, &[&provider.name, &models.len()]).dim());
+            },
+            Err(e) => {
+                eprintln!("{}", tf(MsgKey::ModelsFetchFailed, &[&provider.name, &e]).red());
+            }
+        }
+    }
     Ok(())
 }
 
+/// 最多跟随 `max` 次重定向，0 表示不跟随
+/// 同一地址重复出现时视为循环，提前报错
+fn redirect_policy(max: usize) -> reqwest::redirect::Policy {
+    if max == 0 {
+        return reqwest::redirect::Policy::none();
+    }
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() > max {
+            attempt.error("too many redirects")
+        } else if attempt.previous().contains(attempt.url()) {
+            attempt.error("redirect loop")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// 创建发送所有请求的HTTP客户端
+/// 开启压缩后会发送对应的 Accept-Encoding，并在流式读取时按块解压，
+/// 避免经过压缩网关的SSE数据流被当作原始字节解析
+fn build_client(settings: &Settings) -> Result<reqwest::Client> {
+    let builder = reqwest::Client::builder()
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .redirect(redirect_policy(settings.max_redirects));
+    // 部分代理和网关不能正确处理HTTP/2，可以强制指定协议版本
+    let builder = match settings.http_version {
+        Some(HttpVersion::Http1) => builder.http1_only(),
+        Some(HttpVersion::Http2) => builder.http2_prior_knowledge(),
+        None => builder,
+    };
+    Ok(builder.build()?)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv().ok();
-    let client = reqwest::Client::new();
-    interactive_mode(&client).await
+    let cli = Cli::parse();
+    if let Some(shell) = cli.generate_completions {
+        clap_complete::generate(shell, &mut Cli::command(), "hambur", &mut io::stdout());
+        return Ok(());
+    }
+    let mut settings = Settings::load(&cli);
+    if let Some(path) = &cli.system_prompt_file {
+        let system_prompt = std::fs::read_to_string(path).with_context(|| tf(MsgKey::SystemPromptFileReadFailed, &[path]))?;
+        settings.system_prompt_override = Some(system_prompt.trim_end().to_string());
+    }
+    settings.pins = pins::load();
+    let client = build_client(&settings)?;
+    
+    if cli.list_profiles {
+        for name in Config::profile_names()? {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+    
+    if cli.list_sessions {
+        for entry in sessions::list(&cli.tags) {
+            let tags = if entry.tags.is_empty() { String::new() } else { format!("  [{}]", entry.tags.join(", ")) };
+            println!("{}  {}{}", entry.saved_at, entry.path, tags);
+        }
+        return Ok(());
+    }
+    
+    let config = Config::load(settings.profile.as_deref())?;
+    settings.apply_config(&config);
+    let mut providers = load_providers(&config);
+    if cli.show_config {
+        for line in config_report(&settings, &providers, &cli, &config) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+    for provider in providers.iter().filter(|p| p.wait_for_ready) {
+        wait_for_ready(&client, provider).await;
+    }
+    if cli.refresh_models {
+        refresh_models(&client, &providers).await?;
+        providers = load_providers(&config);
+    }
+    
+    #[cfg(feature = "serve")]
+    if let Some(addr) = cli.serve {
+        return serve::run(client, providers, settings, addr).await;
+    }
+
+    if let Some(prompt) = &cli.bench {
+        // 打字机效果的延迟会计入耗时，测速时关闭
+        settings.stream_delay_ms = 0;
+        return run_bench(&client, &providers, &settings, prompt, cli.bench_runs).await;
+    }
+
+    if cli.stdin_loop {
+        return stdin_loop(&client, &providers, &settings, cli.stateful, cli.max_concurrent).await;
+    }
+    
+    if let Some(prompt) = one_shot_prompt(&cli)? {
+        // 单次模式：只输出回答内容，便于重定向到文件
+        let mut message_history = Vec::new();
+        // 每次运行重新生成文件，断线重连时追加的内容接在本次的回复之后
+        if let Some(path) = &settings.stream_to_file {
+            std::fs::File::create(path).context(tf(MsgKey::StreamFileFailed, &[&path.display()]))?;
+        }
+        if let Err(e) = send_with_hooks(&client, &providers, &prompt, &settings.model, &mut message_history, &settings, TurnOptions::default()).await {
+            report_chat_error(e)?;
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
+    if settings.stream_to_file.is_some() {
+        anyhow::bail!(t(MsgKey::StreamFileOneShot));
+    }
+    interactive_mode(&client, &mut providers, &mut settings, &cli).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::parse_responses;
+    use sse::SseLine;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 两个数据块和结束标记经过gzip压缩后的内容
+    const GZIP_SSE: [u8; 98] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x4b, 0x49, 0x2c, 0x49, 0xb4, 0x52,
+        0xa8, 0x56, 0x4a, 0xce, 0xc8, 0xcf, 0x4c, 0x4e, 0x2d, 0x56, 0xb2, 0x8a, 0xae, 0x56, 0xca, 0xcc,
+        0x4b, 0x49, 0xad, 0x50, 0xb2, 0x32, 0xd0, 0x51, 0x4a, 0x49, 0xcd, 0x29, 0x49, 0x54, 0xb2, 0x02,
+        0x4a, 0xe7, 0xe7, 0x95, 0xa4, 0xe6, 0x95, 0x28, 0x59, 0x29, 0x79, 0xa4, 0xe6, 0xe4, 0xe4, 0x2b,
+        0xd5, 0xd6, 0xc6, 0xd6, 0x72, 0x71, 0xa5, 0x90, 0xaa, 0x59, 0x21, 0xbd, 0x2a, 0xb3, 0x00, 0x45,
+        0x73, 0xb4, 0x8b, 0xbf, 0x9f, 0x6b, 0x2c, 0x17, 0x17, 0x00, 0x50, 0xce, 0xf7, 0xf0, 0x88, 0x00,
+        0x00, 0x00,
+    ];
+
+    #[test]
+    fn reply_from_sse_matches_streaming_rules() {
+        let text = [
+            ": ping",
+            r#"data: {"choices":[{"index":0,"delta":{"content":"AI: Hel"}}]}"#,
+            r#"data: {"choices":[{"index":0,"delta""#,
+            r#"data: :{"content":"lo STOP ignored"}}]}"#,
+            "data: END",
+            r#"data: {"choices":[{"index":0,"delta":{"content":"after"}}]}"#,
+        ].join("\n");
+        let reply = reply_from_sse(&text, Some("data: END"), ContentFilter::new(Some("AI:"), Some("STOP"))).unwrap();
+        assert_eq!(reply, "Hello ");
+
+        let reply = reply_from_sse(&text, Some("data: END"), ContentFilter::default()).unwrap();
+        assert_eq!(reply, "AI: Hello STOP ignored");
+    }
+
+    #[test]
+    fn menu_filter_ignores_case() {
+        let model = Model {
+            id: "openai/GPT-4o".to_string(),
+            name: "GPT-4o".to_string(),
+            provider: "openrouter".to_string(),
+            pricing: None,
+            favorite: false,
+        };
+        let cases = [("gpt", true), ("GPT", true), ("openai/gpt-4o", true), ("", true), ("claude", false)];
+        for (filter, expected) in cases {
+            assert_eq!(matches_menu_filter(&model, &filter.to_lowercase()), expected, "filter: {:?}", filter);
+        }
+    }
+
+    #[test]
+    fn menu_window_keeps_selection_visible() {
+        assert_eq!(menu_window(5, 4, 10), 0..5);
+        assert_eq!(menu_window(100, 0, 10), 0..10);
+        assert_eq!(menu_window(100, 50, 10), 45..55);
+        assert_eq!(menu_window(100, 99, 10), 90..100);
+        // 终端过小时至少显示选中项
+        assert_eq!(menu_window(100, 42, 0), 42..43);
+    }
+
+    #[tokio::test]
+    async fn decodes_gzip_compressed_stream() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                let n = socket.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..n]);
+            }
+            socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n").await.unwrap();
+            // 压缩数据分成两块发送，第一块在数据中间截断
+            for chunk in GZIP_SSE.chunks(40) {
+                socket.write_all(format!("{:x}\r\n", chunk.len()).as_bytes()).await.unwrap();
+                socket.write_all(chunk).await.unwrap();
+                socket.write_all(b"\r\n").await.unwrap();
+                socket.flush().await.unwrap();
+            }
+            socket.write_all(b"0\r\n\r\n").await.unwrap();
+            String::from_utf8_lossy(&request).to_lowercase()
+        });
+
+        let client = build_client(&Settings::default()).unwrap();
+        let mut stream = client.get(format!("http://{}/", addr)).send().await.unwrap().bytes_stream();
+        let mut body = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+
+        let request = server.await.unwrap();
+        assert!(request.lines().any(|line| line.starts_with("accept-encoding:") && line.contains("gzip")));
+        let text = String::from_utf8(body).unwrap();
+        let content: String = text.lines()
+            .filter_map(|line| match sse::classify(line) {
+                SseLine::Data(data) => Some(data),
+                _ => None,
+            })
+            .flat_map(|data| parse_responses(data).unwrap())
+            .flat_map(|response| response.choices)
+            .filter_map(|choice| choice.delta.content)
+            .collect();
+        assert_eq!(content, "Hello gzip");
+    }
 }
@@ -0,0 +1,153 @@
+use crossterm::style::{Color, ContentStyle, StyledContent, Stylize};
+
+use crate::color;
+
+/// 一段文本的显示样式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextStyle {
+    Plain,
+    Bold,
+    Italic,
+    Heading,
+}
+
+impl TextStyle {
+    /// 以该样式渲染一个字符，正文默认为绿色，关闭颜色时不带样式
+    pub fn apply(self, c: char) -> StyledContent<char> {
+        if !color::enabled() {
+            return StyledContent::new(ContentStyle::new(), c);
+        }
+        match self {
+            TextStyle::Plain => c.with(Color::Green),
+            TextStyle::Bold => c.with(Color::Green).bold(),
+            TextStyle::Italic => c.with(Color::Green).underlined(),
+            TextStyle::Heading => c.with(Color::Cyan).bold(),
+        }
+    }
+}
+
+/// 带样式的一段文本
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub text: String,
+    pub style: TextStyle,
+}
+
+impl Segment {
+    pub fn plain(text: &str) -> Self {
+        Self { text: text.to_string(), style: TextStyle::Plain }
+    }
+}
+
+/// 流式输出时的轻量Markdown渲染器
+/// 数据流中的标记可能被拆分在多个数据块中，因此按行缓冲，拿到完整的一行后再转换
+/// `**粗体**`、`*斜体*` 和 `# 标题`；代码块和行内代码中的内容原样输出
+#[derive(Debug, Default)]
+pub struct MarkdownRenderer {
+    pending: String,
+    in_code_block: bool,
+}
+
+impl MarkdownRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一段内容，返回其中已完整的行渲染后的文本段
+    pub fn push(&mut self, content: &str) -> Vec<Segment> {
+        self.pending.push_str(content);
+
+        let mut segments = Vec::new();
+        while let Some(end) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=end).collect();
+            self.render_line(line.trim_end_matches('\n'), &mut segments);
+            segments.push(Segment::plain("\n"));
+        }
+        segments
+    }
+
+    /// 数据流结束时输出缓冲中剩余的不完整行
+    pub fn finish(&mut self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.render_line(&line, &mut segments);
+        }
+        segments
+    }
+
+    fn render_line(&mut self, line: &str, segments: &mut Vec<Segment>) {
+        if line.trim_start().starts_with("```") {
+            self.in_code_block = !self.in_code_block;
+            segments.push(Segment::plain(line));
+            return;
+        }
+        if self.in_code_block {
+            segments.push(Segment::plain(line));
+            return;
+        }
+
+        let hashes = line.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&hashes) && line[hashes..].starts_with(' ') {
+            segments.push(Segment {
+                text: line[hashes..].trim_start().to_string(),
+                style: TextStyle::Heading,
+            });
+            return;
+        }
+
+        render_inline(line, segments);
+    }
+}
+
+/// 转换行内的粗体和斜体标记，没有配对的标记按原文输出
+fn render_inline(line: &str, segments: &mut Vec<Segment>) {
+    let mut text = String::new();
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        let (marker, style) = if rest.starts_with("**") {
+            ("**", TextStyle::Bold)
+        } else if c == '*' {
+            ("*", TextStyle::Italic)
+        } else if c == '`' {
+            // 行内代码原样输出，其中的星号不作为标记
+            match rest[1..].find('`') {
+                Some(end) => {
+                    text.push_str(&rest[..end + 2]);
+                    rest = &rest[end + 2..];
+                },
+                None => {
+                    text.push_str(rest);
+                    rest = "";
+                },
+            }
+            continue;
+        } else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+
+        let inner = &rest[marker.len()..];
+        // 标记后紧跟空白（如列表项 `* item`）或找不到结束标记时不是强调
+        let closing = inner.find(marker).filter(|&end| end > 0 && !inner.starts_with(char::is_whitespace));
+        match closing {
+            Some(end) => {
+                if !text.is_empty() {
+                    segments.push(Segment::plain(&std::mem::take(&mut text)));
+                }
+                segments.push(Segment { text: inner[..end].to_string(), style });
+                rest = &inner[end + marker.len()..];
+            },
+            None => {
+                text.push_str(marker);
+                rest = inner;
+            },
+        }
+    }
+
+    if !text.is_empty() {
+        segments.push(Segment::plain(&text));
+    }
+}
@@ -1,18 +1,87 @@
+use chrono::{DateTime, Local};
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
 
-#[derive(Debug, Clone)]
+use crate::config::Config;
+use crate::headers::build_headers;
+use crate::i18n::{MsgKey, t};
+use crate::paths;
+use crate::secrets::resolve_api_key;
+
+/// 默认使用的模型（gemini-flash）
+pub const DEFAULT_MODEL: &str = "google/gemini-2.0-flash-001";
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct ModelProvider {
     pub name: String,
     pub api_base: String,
     pub api_key_env: String,
+    /// 保存API密钥的文件路径，设置后优先于 `api_key_env`
+    #[serde(default)]
+    pub api_key_file: Option<String>,
+    #[serde(default)]
     pub models: Vec<Model>,
+    /// 切换到该提供商时使用的模型ID，未设置时使用第一个模型
+    #[serde(default)]
+    pub default_model: Option<String>,
+    /// 合并进请求体的额外字段，用于提供商特有的参数（如 OpenRouter 的 `provider`、`transforms`）
+    #[serde(default)]
+    pub extra_body: Option<serde_json::Value>,
+    /// 是否支持assistant消息预填充（请求以一条不完整的assistant消息结尾，模型从该处继续生成）
+    #[serde(default)]
+    pub supports_prefill: bool,
+    /// 客户端限流：每分钟最多发送的请求数，未设置时不限流
+    #[serde(default)]
+    pub rate_limit_rpm: Option<u32>,
+    /// 接口的请求格式，默认为聊天接口
+    #[serde(default)]
+    pub api_style: ApiStyle,
+    /// 是否以 `developer` 角色代替 `system` 发送系统消息（OpenAI 的新模型）
+    #[serde(default)]
+    pub uses_developer_role: bool,
+    /// 除 `[DONE]` 以外表示数据流结束的标记，与去掉 `data: ` 前缀后的数据或整行相同时结束
+    #[serde(default)]
+    pub done_marker: Option<String>,
+    /// 发送前重命名请求体中的顶层字段（原名 -> 新名），用于适配字段名不同的接口，例如 `max_tokens` -> `max_completion_tokens`
+    #[serde(default)]
+    pub field_map: HashMap<String, String>,
+    /// 启动时等待服务就绪（例如与 hambur 同时启动的本地 Ollama）
+    #[serde(default)]
+    pub wait_for_ready: bool,
+    /// 就绪检查请求的地址，未设置时使用 `/models` 接口
+    #[serde(default)]
+    pub health_url: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Model {
     pub id: String,
     pub name: String,
+    /// 所属提供商名称，配置文件中可省略，加载时自动填充
+    #[serde(default)]
     pub provider: String,
+    /// 每百万token的价格（美元），用于估算每次回复的费用
+    #[serde(default)]
+    pub pricing: Option<Pricing>,
+    /// 收藏的模型在搜索结果和选择菜单中排在前面
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+/// 模型的token价格，单位为美元/百万token
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Pricing {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl Pricing {
+    /// 根据用量估算一次请求的费用（美元）
+    pub fn estimate(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input + usage.completion_tokens as f64 * self.output) / 1_000_000.0
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -20,28 +89,466 @@ pub struct ChatRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
     pub stream: bool,
+    /// 请求生成的回复数量，None 时由接口使用默认值（1）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    /// 随机种子，支持的提供商会据此生成可复现的输出
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// 采样温度，None 时使用提供商的默认值
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    /// 要求在数据流末尾返回用量统计，用于估算费用
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct StreamOptions {
+    pub include_usage: bool,
+}
+
+/// 请求体中不允许被 `extra_body` 覆盖的字段
+const RESERVED_REQUEST_FIELDS: [&str; 4] = ["model", "messages", "prompt", "stream"];
+
+/// 提供商接口的请求格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiStyle {
+    /// `/chat/completions`，发送 `messages` 数组
+    #[default]
+    Chat,
+    /// 旧式 `/completions`，发送拼接后的 `prompt` 字符串
+    Completion,
+    /// Google Gemini 原生接口 `streamGenerateContent`，发送 `contents` 数组，密钥通过 `x-goog-api-key` 请求头传递
+    Gemini,
+}
+
+/// 文本补全接口的请求
+#[derive(Debug, Serialize)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub prompt: String,
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+impl ChatRequest {
+    /// 按提供商的接口格式序列化请求，并合并提供商配置的额外字段
+    /// `model`、`messages`/`prompt`、`stream` 始终以请求本身为准，最后按 `field_map` 重命名字段
+    pub fn to_body(&self, provider: &ModelProvider) -> Result<serde_json::Value> {
+        let mut body = match provider.api_style {
+            ApiStyle::Chat => {
+                let mut body = serde_json::to_value(self)?;
+                // 两种系统消息角色按提供商的要求统一，聊天记录中的 `developer` 消息也能发送给旧的提供商
+                let (from, to) = if provider.uses_developer_role { ("system", "developer") } else { ("developer", "system") };
+                if let Some(messages) = body.get_mut("messages").and_then(|m| m.as_array_mut()) {
+                    for message in messages.iter_mut().filter(|m| m["role"] == from) {
+                        message["role"] = to.into();
+                    }
+                    // 附带图片的消息以OpenAI的多模态格式发送
+                    for (message, original) in messages.iter_mut().zip(&self.messages) {
+                        if !original.images.is_empty() {
+                            message["content"] = multimodal_content(original);
+                        }
+                    }
+                }
+                body
+            },
+            ApiStyle::Completion => serde_json::to_value(CompletionRequest {
+                model: self.model.clone(),
+                prompt: flatten_messages(&self.messages),
+                stream: self.stream,
+                n: self.n,
+                seed: self.seed,
+                temperature: self.temperature,
+                top_p: self.top_p,
+                stream_options: self.stream_options,
+            })?,
+            ApiStyle::Gemini => gemini_body(self),
+        };
+        
+        if let (Some(serde_json::Value::Object(extra)), serde_json::Value::Object(fields)) = (&provider.extra_body, &mut body) {
+            for (key, value) in extra {
+                if !RESERVED_REQUEST_FIELDS.contains(&key.as_str()) {
+                    fields.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        
+        // 额外字段中的同名字段也会被重命名
+        if let serde_json::Value::Object(fields) = &mut body {
+            for (from, to) in &provider.field_map {
+                if let Some(value) = fields.remove(from) {
+                    fields.insert(to.clone(), value);
+                }
+            }
+        }
+        
+        Ok(body)
+    }
+}
+
+/// 文本和图片组成的内容数组：`[{"type": "text", ...}, {"type": "image_url", ...}]`
+fn multimodal_content(message: &ChatMessage) -> serde_json::Value {
+    let text = serde_json::json!({ "type": "text", "text": message.content });
+    let images = message.images.iter()
+        .map(|url| serde_json::json!({ "type": "image_url", "image_url": { "url": url } }));
+    std::iter::once(text).chain(images).collect()
+}
+
+/// Gemini 原生接口的请求体
+/// 系统消息合并为 `systemInstruction`，assistant 消息的角色为 `model`，图片以 `inline_data` 发送，采样参数放在 `generationConfig` 中
+fn gemini_body(request: &ChatRequest) -> serde_json::Value {
+    let is_system = |m: &&ChatMessage| m.role == "system" || m.role == "developer";
+    let system: Vec<_> = request.messages.iter()
+        .filter(is_system)
+        .map(|m| serde_json::json!({ "text": m.content }))
+        .collect();
+    let contents: Vec<_> = request.messages.iter()
+        .filter(|m| !is_system(m))
+        .map(|m| {
+            let role = if m.role == "assistant" { "model" } else { "user" };
+            let text = serde_json::json!({ "text": m.content });
+            let images = m.images.iter().filter_map(|url| {
+                let (mime_type, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+                Some(serde_json::json!({ "inline_data": { "mime_type": mime_type, "data": data } }))
+            });
+            serde_json::json!({ "role": role, "parts": std::iter::once(text).chain(images).collect::<Vec<_>>() })
+        })
+        .collect();
+
+    let mut body = serde_json::json!({ "contents": contents });
+    if !system.is_empty() {
+        body["systemInstruction"] = serde_json::json!({ "parts": system });
+    }
+    let mut config = serde_json::Map::new();
+    if let Some(n) = request.n {
+        config.insert("candidateCount".to_string(), n.into());
+    }
+    if let Some(seed) = request.seed {
+        config.insert("seed".to_string(), seed.into());
+    }
+    if let Some(temperature) = request.temperature {
+        config.insert("temperature".to_string(), temperature.into());
+    }
+    if let Some(top_p) = request.top_p {
+        config.insert("topP".to_string(), top_p.into());
+    }
+    if !config.is_empty() {
+        body["generationConfig"] = config.into();
+    }
+    body
+}
+
+/// 将消息列表拼接为带角色标记的补全提示词
+/// 以 `Assistant:` 结尾提示模型开始回复；最后一条是assistant消息（预填充）时，模型直接从该消息继续
+pub fn flatten_messages(messages: &[ChatMessage]) -> String {
+    let mut prompt = String::new();
+    
+    for message in messages {
+        let label = match message.role.as_str() {
+            "system" | "developer" => "System",
+            "assistant" => "Assistant",
+            _ => "User",
+        };
+        if !prompt.is_empty() {
+            prompt.push_str("\n\n");
+        }
+        prompt.push_str(&format!("{}: {}", label, message.content));
+    }
+    
+    if messages.last().is_none_or(|m| m.role != "assistant") {
+        prompt.push_str("\n\nAssistant:");
+    }
+    prompt
 }
 
-#[derive(Debug, Serialize, Clone)]
+/// 聊天记录中允许使用的消息角色
+pub const MESSAGE_ROLES: [&str; 4] = ["system", "developer", "user", "assistant"];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
+    /// 消息创建时的本地时间，仅用于显示，不发送给API
+    #[serde(skip)]
+    pub timestamp: Option<DateTime<Local>>,
+    /// assistant消息的推理内容，只在开启 `--keep-reasoning` 时保存，不发送给API
+    #[serde(skip)]
+    pub reasoning: Option<String>,
+    /// 随消息发送的图片（data URL），发送时与文本一起转换为多模态内容数组
+    #[serde(skip)]
+    pub images: Vec<String>,
+}
+
+impl ChatMessage {
+    /// 创建一条消息并记录当前时间
+    pub fn new(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: content.into(),
+            timestamp: Some(Local::now()),
+            reasoning: None,
+            images: Vec::new(),
+        }
+    }
+}
+
+/// 判断响应中返回的模型是否就是请求的模型
+/// 忽略大小写、提供商前缀（如 `openai/`、`models/`）和请求中的变体后缀（如 `:free`），
+/// 返回的名称可以多出日期或版本后缀（如请求 `gpt-4o` 返回 `gpt-4o-2024-08-06`、`-001` 或 `-latest`），
+/// 但 `gpt-4o-mini` 这样的其他型号不算相同
+pub fn is_same_model(requested: &str, returned: &str) -> bool {
+    let base = |id: &str| id.rsplit('/').next().unwrap_or(id).to_lowercase();
+    let requested = base(requested.split(':').next().unwrap_or(requested));
+    let returned = base(returned);
+    match returned.strip_prefix(&requested) {
+        Some("") => true,
+        Some(rest) => rest.strip_prefix('-').is_some_and(|version| {
+            version == "latest" || (version.starts_with(|c: char| c.is_ascii_digit()) && version.chars().all(|c| c.is_ascii_digit() || c == '-'))
+        }),
+        None => false,
+    }
+}
+
+/// 粗略估算文本的token数：ASCII字符按4个一个token，其他字符（如中文）按每个一个token
+pub fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.bytes().filter(u8::is_ascii).count();
+    let other = text.chars().filter(|c| !c.is_ascii()).count();
+    ascii.div_ceil(4) + other
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ChatResponse {
+    /// 携带用量统计的最后一个数据块中为空
+    #[serde(default)]
     pub choices: Vec<ChatResponseChoice>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    /// 提供商为这次回复分配的ID，每个数据块中相同，反馈问题时可以提供给提供商
+    #[serde(default, alias = "responseId")]
+    pub id: Option<String>,
+    /// 实际生成回复的模型，可能与请求的模型不同
+    #[serde(default, alias = "modelVersion")]
+    pub model: Option<String>,
+    /// Gemini 原生接口返回的候选回复，解析后转换为 `choices`
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+    #[serde(default, rename = "usageMetadata")]
+    usage_metadata: Option<GeminiUsage>,
+    /// 错误帧的 `error` 字段，其余字段都有默认值，因此需要单独识别错误帧
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiCandidate {
+    #[serde(default)]
+    index: u32,
+    #[serde(default)]
+    content: Option<GeminiContent>,
+    #[serde(default, rename = "finishReason")]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiContent {
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiPart {
+    #[serde(default)]
+    text: Option<String>,
+    /// 思考模型的推理内容
+    #[serde(default)]
+    thought: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiUsage {
+    #[serde(default, rename = "promptTokenCount")]
+    prompt_token_count: u64,
+    #[serde(default, rename = "candidatesTokenCount")]
+    candidates_token_count: u64,
+}
+
+impl ChatResponse {
+    /// 将 Gemini 原生格式的候选回复和用量转换为聊天接口的格式，其他格式的响应保持不变
+    fn normalize(mut self) -> Self {
+        for candidate in std::mem::take(&mut self.candidates) {
+            let mut content = None::<String>;
+            let mut reasoning = None::<String>;
+            for part in candidate.content.into_iter().flat_map(|c| c.parts) {
+                if let Some(text) = part.text {
+                    let target = if part.thought { &mut reasoning } else { &mut content };
+                    target.get_or_insert_default().push_str(&text);
+                }
+            }
+            // `MAX_TOKENS` 与聊天接口的 `length` 含义相同
+            let finish_reason = candidate.finish_reason.map(|reason| match reason.as_str() {
+                "MAX_TOKENS" => "length".to_string(),
+                _ => reason.to_lowercase(),
+            });
+            self.choices.push(ChatResponseChoice {
+                index: candidate.index,
+                delta: ChatResponseDelta { content, reasoning_content: reasoning, tool_calls: None },
+                text: None,
+                finish_reason,
+            });
+        }
+        if let Some(usage) = self.usage_metadata.take() {
+            self.usage = Some(Usage {
+                prompt_tokens: usage.prompt_token_count,
+                completion_tokens: usage.candidates_token_count,
+            });
+        }
+        self
+    }
+}
+
+/// 一次请求的token用量
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ChatResponseChoice {
+    /// 请求多个回复时用于区分各个回复，增量按该序号交错返回
+    #[serde(default)]
+    pub index: u32,
+    #[serde(default)]
     pub delta: ChatResponseDelta,
+    /// 文本补全接口返回的内容
+    pub text: Option<String>,
+    /// 生成结束的原因，只在最后一个增量中出现，`length` 表示达到了长度上限
+    #[serde(default)]
+    pub finish_reason: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+impl ChatResponseChoice {
+    /// 本次增量的正文内容，兼容聊天接口的 `delta.content` 和补全接口的 `text`
+    pub fn content(&self) -> Option<&String> {
+        self.delta.content.as_ref().or(self.text.as_ref())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct ChatResponseDelta {
     pub content: Option<String>,
     pub reasoning_content: Option<String>,
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+/// 数据流中的错误帧，例如 `{"error": {"message": "...", "code": 502}}`
+#[derive(Debug, Deserialize)]
+pub struct ErrorResponse {
+    pub error: ApiError,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiError {
+    pub message: String,
+    #[serde(default)]
+    pub code: Option<serde_json::Value>,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.code {
+            Some(code) => write!(f, "{} ({})", self.message, code),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// 解析一行数据中的响应
+/// 部分提供商会把多个JSON对象拼接在同一行中，整行解析失败时再逐个解析；
+/// 带有 `error` 字段的错误帧不是正常的数据块，返回错误，由调用方按 `ErrorResponse` 解析
+pub fn parse_responses(data: &str) -> Result<Vec<ChatResponse>, serde_json::Error> {
+    let responses = match serde_json::from_str::<ChatResponse>(data) {
+        Ok(response) => vec![response],
+        Err(e) => parse_concatenated_responses(data).ok_or(e)?,
+    };
+    if responses.iter().any(|response| response.error.is_some()) {
+        return Err(serde::de::Error::custom("error frame"));
+    }
+    Ok(responses.into_iter().map(ChatResponse::normalize).collect())
+}
+
+/// 逐个解析拼接在一起的多个响应对象
+/// 对象之间可以有空白或多余的 `data:` 前缀，只要有一处无法解析就返回None
+fn parse_concatenated_responses(data: &str) -> Option<Vec<ChatResponse>> {
+    let mut responses = Vec::new();
+    let mut rest = data.trim();
+    
+    while !rest.is_empty() {
+        let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<ChatResponse>();
+        match stream.next() {
+            Some(Ok(response)) => responses.push(response),
+            _ => return None,
+        }
+        rest = rest[stream.byte_offset()..].trim_start();
+        rest = rest.strip_prefix("data:").unwrap_or(rest).trim_start();
+    }
+    
+    if responses.is_empty() { None } else { Some(responses) }
+}
+
+/// 流式响应中的工具调用片段，同一个调用的参数会分散在多个数据块中
+#[derive(Debug, Deserialize)]
+pub struct ToolCallDelta {
+    #[serde(default)]
+    pub index: usize,
+    pub function: Option<FunctionCallDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FunctionCallDelta {
+    pub name: Option<String>,
+    pub arguments: Option<String>,
+}
+
+/// 拼接完成的工具调用
+#[derive(Debug, Default, Clone)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+/// 将工具调用片段按 index 合并到已收到的调用中
+pub fn accumulate_tool_calls(calls: &mut Vec<ToolCall>, deltas: &[ToolCallDelta]) {
+    for delta in deltas {
+        if calls.len() <= delta.index {
+            calls.resize(delta.index + 1, ToolCall::default());
+        }
+        let call = &mut calls[delta.index];
+        if let Some(function) = &delta.function {
+            if let Some(name) = &function.name {
+                call.name.push_str(name);
+            }
+            if let Some(arguments) = &function.arguments {
+                call.arguments.push_str(arguments);
+            }
+        }
+    }
 }
 
 pub fn get_providers() -> Vec<ModelProvider> {
@@ -50,16 +557,31 @@ pub fn get_providers() -> Vec<ModelProvider> {
             name: String::from("deepseek"),
             api_base: String::from("https://ark.cn-beijing.volces.com/api/v3/chat/completions"),
             api_key_env: String::from("OPENAI_API_KEY"),
+            api_key_file: None,
+            extra_body: None,
+            supports_prefill: false,
+            rate_limit_rpm: None,
+            api_style: ApiStyle::Chat,
+            uses_developer_role: false,
+            done_marker: None,
+            field_map: HashMap::new(),
+            wait_for_ready: false,
+            health_url: None,
+            default_model: None,
             models: vec![
                 Model {
                     id: String::from("deepseek-r1-250120"),
                     name: String::from("deepseek-r1"),
                     provider: String::from("deepseek"),
+                    pricing: None,
+                    favorite: false,
                 },
                 Model {
                     id: String::from("deepseek-v3-241226"),
                     name: String::from("deepseek-v3"),
                     provider: String::from("deepseek"),
+                    pricing: None,
+                    favorite: false,
                 },
             ],
         },
@@ -67,42 +589,406 @@ pub fn get_providers() -> Vec<ModelProvider> {
             name: String::from("openrouter"),
             api_base: String::from("https://openrouter.ai/api/v1/chat/completions"),
             api_key_env: String::from("OPENROUTER_API_KEY"),
+            api_key_file: None,
+            extra_body: None,
+            // OpenRouter 对 Anthropic、Gemini 等模型支持预填充
+            supports_prefill: true,
+            rate_limit_rpm: None,
+            api_style: ApiStyle::Chat,
+            uses_developer_role: false,
+            done_marker: None,
+            field_map: HashMap::new(),
+            wait_for_ready: false,
+            health_url: None,
+            default_model: None,
             models: vec![
                 Model {
                     id: String::from("google/gemini-2.0-flash-001"),
                     name: String::from("gemini-flash"),
                     provider: String::from("openrouter"),
+                    pricing: None,
+                    favorite: false,
                 },
                 Model {
                     id: String::from("google/gemini-2.0-flash-lite-001"),
                     name: String::from("gemini-flash-lite"),
                     provider: String::from("openrouter"),
+                    pricing: None,
+                    favorite: false,
                 },
                 Model {
                     id: String::from("google/gemini-2.0-pro-exp-02-05"),
                     name: String::from("gemini-pro"),
                     provider: String::from("openrouter"),
+                    pricing: None,
+                    favorite: false,
                 },
             ],
         },
     ]
 }
 
-pub fn find_models(query: &str) -> Vec<Model> {
-    let providers = get_providers();
+/// 加载提供商列表
+/// 以内置提供商为基础，配置文件中同名的提供商会替换内置配置，其余的追加到列表末尾，
+/// 然后合并缓存中从 `/models` 接口获取的模型，最后只保留配置中启用的提供商
+pub fn load_providers(config: &Config) -> Vec<ModelProvider> {
+    let mut providers = get_providers();
+    
+    for mut provider in config.providers.iter().cloned() {
+        for model in &mut provider.models {
+            model.provider = provider.name.clone();
+        }
+        match providers.iter_mut().find(|p| p.name == provider.name) {
+            Some(existing) => *existing = provider,
+            None => providers.push(provider),
+        }
+    }
+    
+    let cache = read_models_cache();
+    for provider in &mut providers {
+        if let Some(ids) = cache.get(&provider.name) {
+            merge_models(provider, ids);
+        }
+    }
+    
+    let favorites = read_favorites();
+    for model in providers.iter_mut().flat_map(|p| p.models.iter_mut()) {
+        if let Some(&favorite) = favorites.get(&model.id) {
+            model.favorite = favorite;
+        }
+    }
+    
+    if let Some(enabled) = &config.enabled_providers {
+        providers.retain(|p| enabled.contains(&p.name));
+    }
+    
+    providers
+}
+
+/// 将模型ID合并到提供商的模型列表中，已配置的模型保持不变
+fn merge_models(provider: &mut ModelProvider, ids: &[String]) {
+    for id in ids {
+        if !provider.models.iter().any(|m| &m.id == id) {
+            provider.models.push(Model {
+                id: id.clone(),
+                name: id.clone(),
+                provider: provider.name.clone(),
+                pricing: None,
+                favorite: false,
+            });
+        }
+    }
+}
+
+pub fn find_models(providers: &[ModelProvider], query: &str) -> Vec<Model> {
     let mut matches = Vec::new();
     
     for provider in providers {
-        for model in provider.models {
+        for model in &provider.models {
             if model.name.contains(query) || model.id.contains(query) {
-                matches.push(model);
+                matches.push(model.clone());
             }
         }
     }
     
+    // 收藏的模型排在前面，其余保持原有顺序
+    matches.sort_by_key(|m| !m.favorite);
     matches
 }
 
-pub fn get_provider_by_model(model_id: &str) -> Option<ModelProvider> {
-    get_providers().into_iter().find(|p| p.models.iter().any(|m| m.id == model_id))
-}
\ No newline at end of file
+impl ModelProvider {
+    /// 切换到该提供商时使用的模型：配置的 `default_model`，找不到时为第一个模型
+    pub fn default_model(&self) -> Option<&Model> {
+        self.default_model.as_ref()
+            .and_then(|id| self.models.iter().find(|m| &m.id == id))
+            .or_else(|| self.models.first())
+    }
+
+    /// 聊天请求的地址，Gemini 原生接口的地址中包含模型ID
+    pub fn request_url(&self, model_id: &str) -> String {
+        match self.api_style {
+            ApiStyle::Gemini => format!("{}/models/{}:streamGenerateContent?alt=sse", self.api_base.trim_end_matches('/'), model_id),
+            _ => self.api_base.clone(),
+        }
+    }
+}
+
+pub fn get_provider_by_model<'a>(providers: &'a [ModelProvider], model_id: &str) -> Option<&'a ModelProvider> {
+    providers.iter().find(|p| p.models.iter().any(|m| m.id == model_id))
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelListResponse {
+    #[serde(default)]
+    data: Vec<ModelListEntry>,
+    /// Gemini 原生接口的模型列表，名称形如 `models/gemini-2.0-flash`
+    #[serde(default)]
+    models: Vec<GeminiModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiModelEntry {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelListEntry {
+    id: String,
+}
+
+/// 根据聊天接口地址推导出模型列表接口地址
+/// 例如 `https://openrouter.ai/api/v1/chat/completions` -> `https://openrouter.ai/api/v1/models`
+fn models_url(api_base: &str) -> String {
+    let base = api_base.trim_end_matches('/');
+    let base = base.strip_suffix("/chat/completions").unwrap_or(base);
+    format!("{}/models", base)
+}
+
+/// 就绪检查的地址
+pub fn health_url(provider: &ModelProvider) -> String {
+    provider.health_url.clone().unwrap_or_else(|| models_url(&provider.api_base))
+}
+
+/// 从提供商的 OpenAI 兼容 `/models` 接口（或 Gemini 原生的模型列表接口）获取可用模型
+pub async fn fetch_models(client: &reqwest::Client, provider: &ModelProvider) -> Result<Vec<Model>> {
+    let api_key = resolve_api_key(provider)?;
+    let response: ModelListResponse = client
+        .get(models_url(&provider.api_base))
+        .headers(build_headers(&api_key, provider)?)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context(t(MsgKey::ModelListParseFailed))?;
+    
+    let gemini_ids = response.models.into_iter()
+        .map(|entry| entry.name.strip_prefix("models/").map(str::to_string).unwrap_or(entry.name));
+    Ok(response.data
+        .into_iter()
+        .map(|entry| entry.id)
+        .chain(gemini_ids)
+        .map(|id| Model {
+            name: id.clone(),
+            id,
+            provider: provider.name.clone(),
+            pricing: None,
+            favorite: false,
+        })
+        .collect())
+}
+
+fn favorites_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("favorites.json"))
+}
+
+/// 读取通过 `/fav` 修改的收藏状态（模型ID -> 是否收藏），优先于配置文件中的 `favorite`
+fn read_favorites() -> HashMap<String, bool> {
+    favorites_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// 切换模型的收藏状态并保存，返回切换后的状态
+pub fn toggle_favorite(providers: &mut [ModelProvider], model_id: &str) -> Result<bool> {
+    let favorite = !providers.iter()
+        .flat_map(|p| &p.models)
+        .any(|m| m.id == model_id && m.favorite);
+    for model in providers.iter_mut().flat_map(|p| p.models.iter_mut()).filter(|m| m.id == model_id) {
+        model.favorite = favorite;
+    }
+    
+    let path = favorites_path().context(t(MsgKey::CacheDirUnknown))?;
+    let mut favorites = read_favorites();
+    favorites.insert(model_id.to_string(), favorite);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(&favorites)?)?;
+    Ok(favorite)
+}
+
+fn last_model_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("last_model"))
+}
+
+/// 读取上次退出交互模式时使用的模型ID
+pub fn read_last_model() -> Option<String> {
+    last_model_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|text| text.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+/// 保存退出交互模式时使用的模型ID，下次启动时恢复
+pub fn write_last_model(model_id: &str) -> Result<()> {
+    let path = last_model_path().context(t(MsgKey::CacheDirUnknown))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, model_id)?;
+    Ok(())
+}
+
+fn models_cache_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("models.json"))
+}
+
+/// 读取模型缓存（提供商名称 -> 模型ID列表），缓存不存在或损坏时返回空表
+fn read_models_cache() -> HashMap<String, Vec<String>> {
+    models_cache_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// 更新缓存中某个提供商的模型列表
+pub fn write_models_cache(provider_name: &str, models: &[Model]) -> Result<()> {
+    let path = models_cache_path().context(t(MsgKey::CacheDirUnknown))?;
+    let mut cache = read_models_cache();
+    cache.insert(provider_name.to_string(), models.iter().map(|m| m.id.clone()).collect());
+    
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(&cache)?)?;
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(config: serde_json::Value) -> ModelProvider {
+        let mut base = serde_json::json!({ "name": "test", "api_base": "http://localhost/v1", "api_key_env": "TEST_KEY" });
+        base.as_object_mut().unwrap().extend(config.as_object().unwrap().clone());
+        serde_json::from_value(base).unwrap()
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            model: "gpt-4o".to_string(),
+            messages: vec![ChatMessage::new("user", "hi")],
+            stream: true,
+            n: None,
+            seed: None,
+            temperature: None,
+            top_p: None,
+            stream_options: None,
+        }
+    }
+
+    #[test]
+    fn field_map_renames_max_tokens() {
+        let provider = provider(serde_json::json!({
+            "extra_body": { "max_tokens": 100 },
+            "field_map": { "max_tokens": "max_completion_tokens" },
+        }));
+        let body = request().to_body(&provider).unwrap();
+        assert_eq!(body["max_completion_tokens"], 100);
+        assert!(body.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn extra_body_cannot_overwrite_reserved_fields() {
+        let provider = provider(serde_json::json!({
+            "extra_body": { "model": "other", "messages": [], "stream": false, "transforms": ["middle-out"] },
+        }));
+        let body = request().to_body(&provider).unwrap();
+        assert_eq!(body["model"], "gpt-4o");
+        assert_eq!(body["messages"][0]["content"], "hi");
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["transforms"][0], "middle-out");
+    }
+
+    fn contents(responses: &[ChatResponse]) -> Vec<&str> {
+        responses.iter()
+            .flat_map(|response| &response.choices)
+            .filter_map(|choice| choice.content().map(String::as_str))
+            .collect()
+    }
+
+    #[test]
+    fn parses_concatenated_objects_in_one_line() {
+        let data = r#"{"choices":[{"index":0,"delta":{"content":"Hel"}}]}{"choices":[{"index":0,"delta":{"content":"lo"}}]}"#;
+        assert_eq!(contents(&parse_responses(data).unwrap()), ["Hel", "lo"]);
+
+        // 对象之间带有空白和多余的 `data:` 前缀
+        let data = r#"{"choices":[{"index":0,"delta":{"content":"a"}}]} data: {"choices":[{"index":0,"delta":{"content":"b"}}]}"#;
+        assert_eq!(contents(&parse_responses(data).unwrap()), ["a", "b"]);
+    }
+
+    #[test]
+    fn frame_split_across_chunks_parses_once_joined() {
+        let frame = r#"{"choices":[{"index":0,"delta":{"content":"Hello world"}}]}"#;
+        let (head, tail) = frame.split_at(20);
+        // 单独的前半部分无法解析，与下一块拼接后得到完整的数据
+        assert!(parse_responses(head).is_err());
+        assert!(parse_responses(tail).is_err());
+        let joined = format!("{}{}", head, tail);
+        assert_eq!(contents(&parse_responses(&joined).unwrap()), ["Hello world"]);
+    }
+
+    #[test]
+    fn error_frame_mid_stream_is_not_a_chunk() {
+        let stream = [
+            r#"{"choices":[{"index":0,"delta":{"content":"Hel"}}]}"#,
+            r#"{"error":{"message":"Upstream provider failed","code":502}}"#,
+        ];
+        assert_eq!(contents(&parse_responses(stream[0]).unwrap()), ["Hel"]);
+        assert!(parse_responses(stream[1]).is_err());
+        let error: ErrorResponse = serde_json::from_str(stream[1]).unwrap();
+        assert_eq!(error.error.to_string(), "Upstream provider failed (502)");
+    }
+
+    #[test]
+    fn usage_only_chunk_differs_from_error_frame() {
+        let usage = r#"{"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":34}}"#;
+        let response = parse_responses(usage).unwrap().remove(0);
+        assert!(response.choices.is_empty());
+        assert_eq!(response.usage.map(|u| (u.prompt_tokens, u.completion_tokens)), Some((12, 34)));
+
+        let error = r#"{"error":{"message":"Rate limited"}}"#;
+        assert!(parse_responses(error).is_err());
+        assert!(serde_json::from_str::<ErrorResponse>(usage).is_err());
+    }
+
+    #[test]
+    fn same_model_allows_provider_prefix_and_version_suffix() {
+        let cases = [
+            ("gpt-4o", "gpt-4o"),
+            ("gpt-4o", "gpt-4o-2024-08-06"),
+            ("openai/gpt-4o", "openai/gpt-4o-2024-08-06"),
+            ("gemini-2.0-flash", "gemini-2.0-flash-001"),
+            ("gemini-2.0-flash", "models/gemini-2.0-flash"),
+            ("claude-3-5-sonnet", "claude-3-5-sonnet-latest"),
+            ("meta-llama/llama-3-8b:free", "meta-llama/llama-3-8b"),
+            ("GPT-4o", "gpt-4o"),
+        ];
+        for (requested, returned) in cases {
+            assert!(is_same_model(requested, returned), "{} / {}", requested, returned);
+        }
+    }
+
+    #[test]
+    fn different_model_is_reported() {
+        let cases = [
+            ("gpt-4o", "gpt-4o-mini"),
+            ("gemini-2.0-flash", "gemini-2.0-flash-lite"),
+            ("gpt-4o", "gpt-4o1"),
+            ("openai/gpt-4o", "anthropic/claude-3-5-sonnet"),
+            ("gpt-4o", "gpt-4"),
+        ];
+        for (requested, returned) in cases {
+            assert!(!is_same_model(requested, returned), "{} / {}", requested, returned);
+        }
+    }
+
+    #[test]
+    fn parses_id_and_model_from_chunk() {
+        let data = r#"{"id":"gen-123","model":"openai/gpt-4o-mini","choices":[{"index":0,"delta":{"content":"Hi"}}]}"#;
+        let response = parse_responses(data).unwrap().remove(0);
+        assert_eq!(response.id.as_deref(), Some("gen-123"));
+        assert!(!is_same_model("openai/gpt-4o", response.model.as_deref().unwrap()));
+    }
+}
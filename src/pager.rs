@@ -0,0 +1,110 @@
+use anyhow::Result;
+use crossterm::{cursor::{Hide, MoveTo, Show},
+                event::{read, Event, KeyCode, KeyEvent, KeyEventKind},
+                execute, queue,
+                style::Print,
+                terminal::{self, Clear, ClearType}};
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthChar;
+
+use crate::color::Paint;
+use crate::i18n::{MsgKey, tf};
+use crate::terminal::FullScreenGuard;
+
+/// 按终端宽度折行后的文本行数是否超出一屏
+pub fn exceeds_screen(text: &str) -> bool {
+    match terminal::size() {
+        Ok((width, height)) => wrap_lines(text, width as usize).len() > height as usize,
+        Err(_) => false,
+    }
+}
+
+/// 在备用屏幕中分页显示文本，按 q 或 ESC 返回主屏幕
+pub fn page(text: &str) -> Result<()> {
+    // 无论浏览过程中是否出错，守卫都会回到主屏幕并恢复终端模式
+    let _screen_guard = FullScreenGuard::enter()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, Hide)?;
+
+    let result = run(&mut stdout, text);
+
+    execute!(stdout, Show)?;
+    result
+}
+
+fn run(stdout: &mut io::Stdout, text: &str) -> Result<()> {
+    let mut top = 0;
+
+    loop {
+        let (width, height) = terminal::size()?;
+        let lines = wrap_lines(text, width as usize);
+        // 最后一行留给状态栏
+        let page_height = (height as usize).saturating_sub(1).max(1);
+        let max_top = lines.len().saturating_sub(page_height);
+        top = top.min(max_top);
+
+        queue!(stdout, MoveTo(0, 0), Clear(ClearType::All))?;
+        for (row, line) in lines.iter().skip(top).take(page_height).enumerate() {
+            queue!(stdout, MoveTo(0, row as u16), Print(line))?;
+        }
+        let last = (top + page_height).min(lines.len());
+        let status = tf(MsgKey::PagerStatus, &[&(top + 1), &last, &lines.len()]);
+        queue!(stdout, MoveTo(0, height.saturating_sub(1)), Print(status.reverse()))?;
+        stdout.flush()?;
+
+        if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) = read()? {
+            match code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => top = top.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => top = (top + 1).min(max_top),
+                KeyCode::PageUp => top = top.saturating_sub(page_height),
+                KeyCode::PageDown | KeyCode::Char(' ') => top = (top + page_height).min(max_top),
+                KeyCode::Home | KeyCode::Char('g') => top = 0,
+                KeyCode::End | KeyCode::Char('G') => top = max_top,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// 按终端宽度将文本折成多行，按显示宽度累计：中文和emoji占两列，放不下时整个字符移到下一行
+fn wrap_lines(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+
+    for line in text.lines() {
+        let mut current = String::new();
+        let mut current_width = 0;
+        for c in line.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if current_width + char_width > width && !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+                current_width = 0;
+            }
+            current.push(c);
+            current_width += char_width;
+        }
+        lines.push(current);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_ascii_by_width() {
+        assert_eq!(wrap_lines("abcdefg\n\nhi", 3), ["abc", "def", "g", "", "hi"]);
+    }
+
+    #[test]
+    fn wraps_wide_characters_by_display_width() {
+        // 每个中文字符占两列，4列的终端一行只能放两个
+        assert_eq!(wrap_lines("你好世界啊", 4), ["你好", "世界", "啊"]);
+        // 剩余一列放不下宽字符时换到下一行
+        assert_eq!(wrap_lines("a你好", 4), ["a你", "好"]);
+        assert_eq!(wrap_lines("🙂🙂🙂", 5), ["🙂🙂", "🙂"]);
+    }
+}
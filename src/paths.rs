@@ -0,0 +1,34 @@
+use std::env;
+use std::path::PathBuf;
+
+/// hambur 的缓存目录
+/// 优先使用 `XDG_CACHE_HOME`，否则为 `~/.cache/hambur`
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("XDG_CACHE_HOME") {
+        return Some(PathBuf::from(dir).join("hambur"));
+    }
+    home_dir().map(|home| home.join(".cache").join("hambur"))
+}
+
+/// hambur 的配置目录
+/// 优先使用 `XDG_CONFIG_HOME`，否则为 `~/.config/hambur`
+pub fn config_dir() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("hambur"));
+    }
+    home_dir().map(|home| home.join(".config").join("hambur"))
+}
+
+/// 将以 `~/` 开头的路径展开为用户主目录下的路径
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
@@ -0,0 +1,28 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use crate::i18n::{MsgKey, t};
+use crate::paths;
+
+/// 读取通过 `/pin` 固定的消息，文件不存在或损坏时返回空列表
+/// 固定的消息保存在缓存目录的 `pins.json` 中，不受 `clear` 影响，重启后仍然有效
+pub fn load() -> Vec<String> {
+    pins_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// 保存固定的消息
+pub fn save(pins: &[String]) -> Result<()> {
+    let path = pins_path().context(t(MsgKey::CacheDirUnknown))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(pins)?)?;
+    Ok(())
+}
+
+fn pins_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("pins.json"))
+}
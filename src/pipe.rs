@@ -0,0 +1,46 @@
+use std::io::{self, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+
+/// 将回复内容写入外部命令的标准输入，例如 `bat -l md`
+/// 命令通过系统shell启动，输出直接显示在终端中；被丢弃时关闭标准输入并等待命令结束
+pub struct OutputPipe {
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl OutputPipe {
+    /// 通过shell启动外部命令
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let mut child = if cfg!(windows) {
+            Command::new("cmd").args(["/C", command]).stdin(Stdio::piped()).spawn()?
+        } else {
+            Command::new("sh").args(["-c", command]).stdin(Stdio::piped()).spawn()?
+        };
+        let stdin = child.stdin.take();
+        Ok(Self { child, stdin })
+    }
+
+    /// 写入一段内容并立即刷新，让外部命令能够逐步处理
+    pub fn write(&mut self, text: &str) -> io::Result<()> {
+        match self.stdin.as_mut() {
+            Some(stdin) => {
+                stdin.write_all(text.as_bytes())?;
+                stdin.flush()
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// 关闭标准输入，等待外部命令处理完剩余内容后退出
+    pub fn finish(mut self) -> io::Result<ExitStatus> {
+        self.stdin.take();
+        self.child.wait()
+    }
+}
+
+impl Drop for OutputPipe {
+    fn drop(&mut self) {
+        self.stdin.take();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// 全局限流器，按提供商名称区分
+pub static LIMITER: LazyLock<RateLimiter> = LazyLock::new(RateLimiter::new);
+
+/// 令牌桶
+struct Bucket {
+    /// 当前可用令牌数，为负数时表示已被预占的待发请求
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// 按键区分的令牌桶限流器
+/// 桶容量为每分钟请求数，令牌以 rpm/60 每秒的速度补充
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self { buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// 预占一个令牌，返回发送请求前需要等待的时长
+    /// 预占在加锁时完成，多个并发请求会依次排队而不会同时放行
+    pub fn reserve(&self, key: &str, rpm: u32) -> Duration {
+        if rpm == 0 {
+            return Duration::ZERO;
+        }
+
+        let capacity = rpm as f64;
+        let rate = capacity / 60.0;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.last_refill = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        }
+    }
+}
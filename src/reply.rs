@@ -0,0 +1,99 @@
+use crate::models::ChatMessage;
+
+/// 回复中的一类内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    Reasoning,
+    Content,
+}
+
+/// 流式接收的回复，推理内容和正文可能在数据块之间交错到达
+/// 两者分别累积，聊天记录中的消息内容只包含正文，推理内容只在需要时作为单独的字段保存
+#[derive(Debug, Default)]
+pub struct ReplyParts {
+    content: String,
+    reasoning: String,
+    /// 最近显示的是否为推理内容
+    showing_reasoning: bool,
+}
+
+impl ReplyParts {
+    pub fn push_content(&mut self, text: &str) {
+        self.content.push_str(text);
+    }
+
+    pub fn push_reasoning(&mut self, text: &str) {
+        self.reasoning.push_str(text);
+    }
+
+    /// 即将显示一段内容，返回是否从另一类内容切换过来，切换时调用方先结束当前行再输出
+    /// 回复开头的推理内容也算作切换，以便与预填充等已输出的内容分行
+    pub fn switch_to(&mut self, part: Part) -> bool {
+        let was_reasoning = std::mem::replace(&mut self.showing_reasoning, part == Part::Reasoning);
+        was_reasoning != self.showing_reasoning
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn has_reasoning(&self) -> bool {
+        !self.reasoning.is_empty()
+    }
+
+    /// 生成保存到聊天记录中的assistant消息，`keep_reasoning` 时附带完整的推理内容
+    pub fn into_message(self, keep_reasoning: bool) -> ChatMessage {
+        let mut message = ChatMessage::new("assistant", self.content);
+        if keep_reasoning && !self.reasoning.is_empty() {
+            message.reasoning = Some(self.reasoning);
+        }
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按 `send_chat_request` 的方式输出数据块，切换内容类型且不在行首时先换行
+    fn render(chunks: &[(Part, &str)]) -> (String, ReplyParts) {
+        let mut parts = ReplyParts::default();
+        let mut output = String::new();
+        for &(part, text) in chunks {
+            match part {
+                Part::Reasoning => parts.push_reasoning(text),
+                Part::Content => parts.push_content(text),
+            }
+            if parts.switch_to(part) && !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+            output.push_str(text);
+        }
+        (output, parts)
+    }
+
+    #[test]
+    fn interleaved_chunks_render_in_order() {
+        let (output, parts) = render(&[
+            (Part::Reasoning, "think "),
+            (Part::Reasoning, "more"),
+            (Part::Content, "Hello"),
+            (Part::Reasoning, "again"),
+            (Part::Content, " world"),
+        ]);
+        assert_eq!(output, "think more\nHello\nagain\n world");
+        assert_eq!(parts.content(), "Hello world");
+    }
+
+    #[test]
+    fn reasoning_never_leaks_into_history() {
+        let chunks = [(Part::Reasoning, "secret"), (Part::Content, "answer"), (Part::Reasoning, "plan")];
+        let message = render(&chunks).1.into_message(false);
+        assert_eq!(message.content, "answer");
+        assert_eq!(message.reasoning, None);
+
+        let message = render(&chunks).1.into_message(true);
+        assert_eq!(message.content, "answer");
+        assert_eq!(message.reasoning.as_deref(), Some("secretplan"));
+    }
+}
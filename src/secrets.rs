@@ -0,0 +1,59 @@
+use anyhow::{Context, Result, bail};
+use std::env;
+use std::path::PathBuf;
+
+use crate::i18n::{MsgKey, tf};
+use crate::models::ModelProvider;
+use crate::paths;
+
+/// 系统钥匙串中保存密钥时使用的服务名，账户名为提供商名称
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "hambur";
+
+/// API密钥的来源
+#[derive(Debug)]
+pub enum KeySource {
+    #[cfg(feature = "keyring")]
+    Keyring,
+    File(PathBuf),
+    Env(String),
+}
+
+impl KeySource {
+    pub fn label(&self) -> String {
+        match self {
+            #[cfg(feature = "keyring")]
+            KeySource::Keyring => crate::i18n::t(MsgKey::KeySourceKeyring).to_string(),
+            KeySource::File(path) => tf(MsgKey::KeySourceFile, &[&path.display()]),
+            KeySource::Env(name) => tf(MsgKey::KeySourceEnv, &[name]),
+        }
+    }
+}
+
+/// 获取提供商的API密钥
+/// 依次尝试系统钥匙串（需启用 `keyring` 特性）、`api_key_file` 指定的文件和 `api_key_env` 环境变量
+pub fn resolve_api_key(provider: &ModelProvider) -> Result<String> {
+    resolve_api_key_with_source(provider).map(|(key, _)| key)
+}
+
+/// 获取提供商的API密钥及其来源，查找顺序与 `resolve_api_key` 相同
+pub fn resolve_api_key_with_source(provider: &ModelProvider) -> Result<(String, KeySource)> {
+    #[cfg(feature = "keyring")]
+    if let Ok(key) = keyring::Entry::new(KEYRING_SERVICE, &provider.name).and_then(|entry| entry.get_password()) {
+        return Ok((key, KeySource::Keyring));
+    }
+
+    if let Some(file) = &provider.api_key_file {
+        let path = paths::expand_home(file);
+        let key = std::fs::read_to_string(&path)
+            .context(tf(MsgKey::KeyFileReadFailed, &[&path.display()]))?;
+        // 文件末尾通常带有换行
+        return Ok((key.trim().to_string(), KeySource::File(path)));
+    }
+
+    if let Ok(key) = env::var(&provider.api_key_env) {
+        return Ok((key, KeySource::Env(provider.api_key_env.clone())));
+    }
+
+    bail!(tf(MsgKey::ApiKeyNotFound, &[&provider.name, &provider.api_key_env]))
+}
@@ -0,0 +1,281 @@
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use futures::future::Either;
+use http_body_util::{BodyExt, Full, StreamBody, combinators::BoxBody};
+use hyper::body::{Bytes, Frame, Incoming};
+use hyper::header::{CONTENT_TYPE, HOST, ORIGIN};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{HeaderMap, Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::net::TcpListener;
+
+use crate::color::Paint;
+use crate::headers::{build_headers, insert_extra};
+use crate::i18n::{MsgKey, t, tf};
+use crate::models::{ApiStyle, ChatMessage, ChatRequest, ErrorResponse, ModelProvider, get_provider_by_model, parse_responses};
+use crate::secrets::resolve_api_key;
+use crate::settings::Settings;
+use crate::sse::{self, SseLine};
+
+type Body = BoxBody<Bytes, io::Error>;
+
+/// 内置的聊天页面，通过 `/chat` 接口逐块读取SSE数据并显示
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html lang="zh">
+<head><meta charset="utf-8"><title>Hambur</title>
+<style>body{font-family:sans-serif;max-width:48rem;margin:2rem auto}pre{white-space:pre-wrap}textarea{width:100%}</style>
+</head>
+<body>
+<div id="log"></div>
+<textarea id="input" rows="4" placeholder="输入问题，Ctrl+Enter 发送"></textarea>
+<script>
+const messages = [];
+const log = document.getElementById('log');
+const input = document.getElementById('input');
+function append(role, text) {
+  const pre = document.createElement('pre');
+  pre.textContent = (role === 'user' ? '你: ' : 'AI: ') + text;
+  log.appendChild(pre);
+  return pre;
+}
+input.addEventListener('keydown', async (event) => {
+  if (event.key !== 'Enter' || !event.ctrlKey || !input.value.trim()) return;
+  const content = input.value;
+  input.value = '';
+  messages.push({ role: 'user', content });
+  append('user', content);
+  const pre = append('assistant', '');
+  const response = await fetch('/chat', { method: 'POST', body: JSON.stringify({ messages }) });
+  const reader = response.body.getReader();
+  const decoder = new TextDecoder();
+  let buffer = '', reply = '';
+  for (;;) {
+    const { done, value } = await reader.read();
+    if (done) break;
+    buffer += decoder.decode(value, { stream: true });
+    const lines = buffer.split('\n');
+    buffer = lines.pop();
+    for (const line of lines) {
+      if (!line.startsWith('data:') || line.includes('[DONE]')) continue;
+      try {
+        const delta = JSON.parse(line.slice(5)).choices?.[0]?.delta?.content;
+        if (delta) { reply += delta; pre.textContent = 'AI: ' + reply; }
+      } catch (e) {}
+    }
+  }
+  if (!response.ok) pre.textContent = 'AI: ' + buffer;
+  messages.push({ role: 'assistant', content: reply });
+});
+</script>
+</body>
+</html>
+"#;
+
+/// `/chat` 接口的请求体，未指定模型时使用默认模型
+#[derive(Debug, Deserialize)]
+struct ChatBody {
+    #[serde(default)]
+    model: Option<String>,
+    messages: Vec<ChatMessage>,
+}
+
+struct ServeState {
+    client: reqwest::Client,
+    providers: Vec<ModelProvider>,
+    settings: Settings,
+    addr: SocketAddr,
+}
+
+/// 在本地启动HTTP服务：`GET /` 返回内置的聊天页面，`POST /chat` 把请求转发给模型对应的提供商，
+/// 并以聊天接口的SSE格式返回回复，提供商、密钥、系统提示词和 `extra_body` 的处理与终端模式相同
+///
+/// 服务使用本地配置的API密钥，因此只接受 `Host` 为监听地址、`Origin` 为同源或不存在的请求，
+/// 防止用户访问的其他网页通过浏览器（包括DNS重绑定）调用该服务
+pub async fn run(client: reqwest::Client, providers: Vec<ModelProvider>, settings: Settings, addr: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(addr).await.with_context(|| tf(MsgKey::ServeBindFailed, &[&addr]))?;
+    eprintln!("{}", tf(MsgKey::ServeListening, &[&addr]).green());
+    let state = Arc::new(ServeState { client, providers, settings, addr });
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(state.clone(), request));
+            // 单个连接出错（例如浏览器中途关闭页面）不影响服务
+            let _ = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await;
+        });
+    }
+}
+
+async fn handle(state: Arc<ServeState>, request: Request<Incoming>) -> Result<Response<Body>, Infallible> {
+    if !is_trusted(request.headers(), state.addr) {
+        return Ok(full(StatusCode::FORBIDDEN, "text/plain; charset=utf-8", t(MsgKey::ServeForbidden).to_string()));
+    }
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/") => full(StatusCode::OK, "text/html; charset=utf-8", INDEX_HTML.to_string()),
+        (&Method::POST, "/chat") => match chat(&state, request).await {
+            Ok(response) => response,
+            Err(e) => full(StatusCode::BAD_GATEWAY, "text/plain; charset=utf-8", format!("{:#}", e)),
+        },
+        _ => full(StatusCode::NOT_FOUND, "text/plain; charset=utf-8", "not found".to_string()),
+    };
+    Ok(response)
+}
+
+async fn chat(state: &ServeState, request: Request<Incoming>) -> Result<Response<Body>> {
+    let bytes = request.into_body().collect().await?.to_bytes();
+    let body: ChatBody = match serde_json::from_slice(&bytes) {
+        Ok(body) => body,
+        Err(e) => return Ok(full(StatusCode::BAD_REQUEST, "text/plain; charset=utf-8", e.to_string())),
+    };
+
+    let model_id = body.model.unwrap_or_else(|| state.settings.model.clone());
+    let Some(provider) = get_provider_by_model(&state.providers, &model_id) else {
+        return Ok(full(StatusCode::BAD_REQUEST, "text/plain; charset=utf-8", tf(MsgKey::ModelProviderNotFound, &[&model_id])));
+    };
+    eprintln!("{}", tf(MsgKey::ServeRequest, &[&model_id, &body.messages.len()]).dim());
+
+    let mut chat_request = ChatRequest {
+        model: model_id,
+        messages: body.messages,
+        stream: true,
+        n: None,
+        seed: state.settings.seed,
+        temperature: state.settings.sampling().temperature,
+        top_p: state.settings.sampling().top_p,
+        stream_options: None,
+    };
+    chat_request.messages.splice(0..0, state.settings.preamble());
+
+    let api_key = resolve_api_key(provider)?;
+    let mut headers = build_headers(&api_key, provider)?;
+    insert_extra(&mut headers, &state.settings.extra_headers)?;
+    let upstream = state.client
+        .post(provider.request_url(&chat_request.model))
+        .headers(headers)
+        .json(&chat_request.to_body(provider)?)
+        .send()
+        .await?;
+
+    let status = StatusCode::from_u16(upstream.status().as_u16())?;
+    let content_type = if status.is_success() { "text/event-stream" } else { "application/json" };
+    let stream = upstream.bytes_stream().map(|chunk| chunk.map_err(io::Error::other));
+    // 聊天接口的数据流原样返回，其他格式转换为聊天接口的格式
+    let stream = if provider.api_style == ApiStyle::Chat || !status.is_success() {
+        Either::Left(stream)
+    } else {
+        Either::Right(normalize_stream(stream))
+    };
+    Ok(Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .body(BodyExt::boxed(StreamBody::new(stream.map(|chunk| chunk.map(Frame::data)))))?)
+}
+
+/// 请求是否来自本服务自己：`Host` 必须是监听地址（监听回环地址时也可以是 `localhost`），
+/// 浏览器发出的跨域请求带有 `Origin`，必须与 `Host` 同源；命令行工具不发送 `Origin`，不受影响
+fn is_trusted(headers: &HeaderMap, addr: SocketAddr) -> bool {
+    let Some(host) = headers.get(HOST).and_then(|host| host.to_str().ok()) else {
+        return false;
+    };
+    let host_allowed = host == addr.to_string()
+        || (addr.ip().is_loopback() && host == format!("localhost:{}", addr.port()));
+    let origin_allowed = match headers.get(ORIGIN) {
+        Some(origin) => origin.to_str().is_ok_and(|origin| origin == format!("http://{}", host)),
+        None => true,
+    };
+    host_allowed && origin_allowed
+}
+
+/// 把补全接口和 Gemini 原生接口的SSE数据流转换为聊天接口的格式，最后补上 `[DONE]`
+/// 数据块不一定在行边界处切分，不完整的行留到下一个数据块再转换
+fn normalize_stream(upstream: impl futures::Stream<Item = io::Result<Bytes>>) -> impl futures::Stream<Item = io::Result<Bytes>> {
+    upstream
+        .scan(Vec::new(), |pending: &mut Vec<u8>, chunk| {
+            let converted = chunk.map(|chunk| {
+                pending.extend_from_slice(&chunk);
+                let end = pending.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+                let lines: Vec<u8> = pending.drain(..end).collect();
+                Bytes::from(String::from_utf8_lossy(&lines).lines().filter_map(normalize_line).collect::<String>())
+            });
+            std::future::ready(Some(converted))
+        })
+        .chain(futures::stream::once(std::future::ready(Ok(Bytes::from_static(b"data: [DONE]\n\n")))))
+}
+
+/// 转换一行SSE数据，返回聊天接口格式的事件；错误帧原样保留，其余不携带内容的行丢弃
+fn normalize_line(line: &str) -> Option<String> {
+    let SseLine::Data(data) = sse::classify(line) else {
+        return None;
+    };
+    let Ok(responses) = parse_responses(data) else {
+        return serde_json::from_str::<ErrorResponse>(data).is_ok().then(|| format!("data: {}\n\n", data));
+    };
+    let events: String = responses.iter()
+        .flat_map(|response| &response.choices)
+        .map(|choice| {
+            let chunk = json!({
+                "choices": [{
+                    "index": choice.index,
+                    "delta": { "content": choice.content(), "reasoning_content": choice.delta.reasoning_content },
+                    "finish_reason": choice.finish_reason,
+                }],
+            });
+            format!("data: {}\n\n", chunk)
+        })
+        .collect();
+    (!events.is_empty()).then_some(events)
+}
+
+fn full(status: StatusCode, content_type: &str, text: String) -> Response<Body> {
+    let mut response = Response::new(Full::new(Bytes::from(text)).map_err(|never| match never {}).boxed());
+    *response.status_mut() = status;
+    if let Ok(value) = content_type.parse() {
+        response.headers_mut().insert(CONTENT_TYPE, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        pairs.iter()
+            .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_only_same_origin_requests() {
+        let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        assert!(is_trusted(&headers(&[("host", "127.0.0.1:8080")]), addr));
+        assert!(is_trusted(&headers(&[("host", "localhost:8080"), ("origin", "http://localhost:8080")]), addr));
+        // 其他网页发起的请求，以及通过DNS重绑定指向本机的域名
+        assert!(!is_trusted(&headers(&[("host", "127.0.0.1:8080"), ("origin", "https://example.com")]), addr));
+        assert!(!is_trusted(&headers(&[("host", "evil.example:8080")]), addr));
+        assert!(!is_trusted(&headers(&[]), addr));
+    }
+
+    #[test]
+    fn converts_gemini_and_completion_lines_to_chat_chunks() {
+        let gemini = r#"data: {"candidates":[{"content":{"parts":[{"text":"Hi"}]}}]}"#;
+        let completion = r#"data: {"choices":[{"index":0,"text":"Hi"}]}"#;
+        for line in [gemini, completion] {
+            let event = normalize_line(line).unwrap();
+            let chunk: serde_json::Value = serde_json::from_str(event.trim().strip_prefix("data: ").unwrap()).unwrap();
+            assert_eq!(chunk["choices"][0]["delta"]["content"], "Hi");
+        }
+        assert_eq!(normalize_line(": ping"), None);
+        let error = r#"data: {"error":{"message":"quota"}}"#;
+        assert_eq!(normalize_line(error).as_deref(), Some(format!("{}\n\n", error).as_str()));
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::i18n::{MsgKey, t};
+use crate::paths;
+
+/// 索引中的一条会话记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEntry {
+    /// 保存的文件的绝对路径
+    pub path: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 最近一次保存的本地时间（RFC 3339）
+    pub saved_at: String,
+}
+
+/// 记录一次保存，同一文件再次保存时更新时间和标签
+/// 索引保存在缓存目录的 `sessions.json` 中，通过 `--list-sessions` 查询
+pub fn record(path: &Path, tags: &[String]) -> Result<()> {
+    let index_path = index_path().context(t(MsgKey::CacheDirUnknown))?;
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let entry = SessionEntry {
+        path: path.display().to_string(),
+        tags: tags.to_vec(),
+        saved_at: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+    };
+
+    let mut entries = read_index();
+    entries.retain(|existing| existing.path != entry.path);
+    entries.push(entry);
+    if let Some(dir) = index_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&index_path, serde_json::to_string_pretty(&entries)?)?;
+    Ok(())
+}
+
+/// 按保存时间排列的会话记录，只保留带有全部指定标签的记录
+pub fn list(tags: &[String]) -> Vec<SessionEntry> {
+    let mut entries: Vec<SessionEntry> = read_index()
+        .into_iter()
+        .filter(|entry| tags.iter().all(|tag| entry.tags.contains(tag)))
+        .collect();
+    entries.sort_by(|a, b| a.saved_at.cmp(&b.saved_at));
+    entries
+}
+
+fn index_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("sessions.json"))
+}
+
+/// 读取会话索引，索引不存在或损坏时返回空列表
+fn read_index() -> Vec<SessionEntry> {
+    index_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
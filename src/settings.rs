@@ -0,0 +1,617 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::cli::Cli;
+use crate::config::{Config, Hooks, SamplingPreset};
+use crate::headers;
+use crate::i18n::{MsgKey, t, tf};
+use crate::models::{ChatMessage, DEFAULT_MODEL};
+use crate::paths;
+use crate::pins;
+
+/// 强制使用的HTTP协议版本
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpVersion {
+    Http1,
+    Http2,
+}
+
+impl std::str::FromStr for HttpVersion {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "1" | "1.1" => Ok(HttpVersion::Http1),
+            "2" => Ok(HttpVersion::Http2),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 配置项当前的值来自哪一层
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Default,
+    Env,
+    Cli,
+    Config,
+    /// 缓存目录中 `pins.json` 保存的固定消息
+    Pins,
+    /// 启动后通过 `/set`、`/mode` 等命令修改
+    Runtime,
+}
+
+impl Source {
+    pub fn label(self) -> &'static str {
+        t(match self {
+            Source::Default => MsgKey::SourceDefault,
+            Source::Env => MsgKey::SourceEnv,
+            Source::Cli => MsgKey::SourceCli,
+            Source::Config => MsgKey::SourceConfig,
+            Source::Pins => MsgKey::SourcePins,
+            Source::Runtime => MsgKey::SourceRuntime,
+        })
+    }
+}
+
+/// 不设置采样参数的模式名称
+pub const DEFAULT_MODE: &str = "default";
+
+/// 内置的采样预设
+fn builtin_modes() -> BTreeMap<String, SamplingPreset> {
+    [("creative", 1.0), ("balanced", 0.7), ("precise", 0.2)]
+        .into_iter()
+        .map(|(name, temperature)| (name.to_string(), SamplingPreset { temperature: Some(temperature), top_p: None }))
+        .collect()
+}
+
+/// 运行时设置
+/// 在启动时由默认值、环境变量和命令行参数依次覆盖构造一次，
+/// 之后作为参数传递，避免在流式输出的循环中反复读取环境变量
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// 流式输出时每个字符之间的延迟（毫秒），0 表示关闭打字机效果
+    pub stream_delay_ms: u64,
+    /// 是否输出调试信息
+    pub debug: bool,
+    /// 是否在终端中渲染回复中的Markdown标记
+    pub markdown: bool,
+    /// 接收回复正文的外部命令
+    pub pipe_command: Option<String>,
+    /// 回复正文直接写入的文件
+    pub stream_to_file: Option<PathBuf>,
+    /// 从回复开头去掉的前缀，例如 `Assistant:`
+    pub strip_prefix: Option<String>,
+    /// 客户端停止标记，回复中出现时结束本轮回复
+    pub client_stop: Option<String>,
+    /// 配置文件中的 `pre_send` 和 `post_receive` 钩子
+    pub hooks: Hooks,
+    /// 请求失败时使用的备用模型（模型ID -> 备用模型ID）
+    pub fallbacks: BTreeMap<String, String>,
+    /// 是否原样显示收到的SSE数据行
+    pub raw_sse: bool,
+    /// 流式输出中途断开时的最大重连次数，0 表示不重连
+    pub reconnect_attempts: u32,
+    /// 是否将推理内容随assistant消息一起保存到聊天记录中
+    pub keep_reasoning: bool,
+    /// 最多显示的推理内容字符数，0 表示不限制
+    pub max_reasoning_chars: usize,
+    /// 是否隐藏推理内容
+    pub hide_reasoning: bool,
+    /// 是否只显示推理内容，不显示正文
+    pub only_reasoning: bool,
+    /// 是否在回复接收完整后一次性显示，而不是流式输出
+    pub buffered: bool,
+    /// 隐藏推理内容时显示的提示文字，None 时使用默认的 `思考中`，空字符串表示不显示提示
+    pub thinking_label: Option<String>,
+    /// 连续两次ESC退出程序的时间窗口（毫秒）
+    pub esc_timeout_ms: u64,
+    /// 执行清空记录等无法撤销的操作前是否需要确认
+    pub confirm_destructive: bool,
+    /// 响应中返回的模型与请求的模型不一致时是否提示
+    pub verify_model: bool,
+    /// 强制使用的HTTP协议版本，None 表示由reqwest协商
+    pub http_version: Option<HttpVersion>,
+    /// 请求时最多跟随的重定向次数，0 表示不跟随
+    pub max_redirects: usize,
+    /// 输入提示符下超过该分钟数没有输入时自动清空聊天记录，None 表示不清空
+    pub idle_clear_mins: Option<u64>,
+    /// 是否记录并保存交互模式的输入历史
+    pub input_history: bool,
+    /// 通过 `/set-header` 添加的请求头，只在本次会话中有效
+    pub extra_headers: Vec<(String, String)>,
+    /// 每次发送前追加记录请求内容的文件
+    pub dump_requests: Option<PathBuf>,
+    /// 退出交互模式时自动保存聊天记录的目录，None 表示不自动保存
+    pub autosave_dir: Option<PathBuf>,
+    /// 不带路径的 `/save` 是否请求模型为对话生成标题作为文件名
+    pub autotitle: bool,
+    /// 是否在每轮提示符前显示分隔线
+    pub separators: bool,
+    /// 回复超出一屏时是否在内置分页器中显示
+    pub pager: bool,
+    /// `/history` 中是否显示每条消息的时间
+    pub timestamps: bool,
+    /// 每次请求生成的回复数量（请求参数 `n`）
+    pub completions: u32,
+    /// 随机种子（请求参数 `seed`），用于获得可复现的输出
+    pub seed: Option<u64>,
+    /// 可通过 `/mode` 切换的采样预设，包括内置预设和配置文件中定义的预设
+    pub modes: BTreeMap<String, SamplingPreset>,
+    /// 当前的采样预设名称，None 表示使用提供商的默认参数
+    pub mode: Option<String>,
+    /// 会话费用上限（美元），累计估算费用超过后发送前需要确认
+    pub budget: Option<f64>,
+    /// 每次请求只发送最近的这么多条消息，None 表示发送全部聊天记录
+    pub context_window: Option<usize>,
+    /// 单条消息超过该字符数时发送前需要确认，None 表示不检查
+    pub input_warn_chars: Option<usize>,
+    /// 估算的上下文token数超过该值时，发送前自动将较早的消息压缩为摘要，None 表示不自动压缩
+    pub auto_compact_tokens: Option<usize>,
+    /// 选中的配置档名称
+    pub profile: Option<String>,
+    /// 启动时使用的模型ID
+    pub model: String,
+    /// 每次请求前附加的系统提示词
+    pub system_prompt: Option<String>,
+    /// 通过 `--system-prompt-file` 指定的系统提示词，优先于配置文件
+    pub system_prompt_override: Option<String>,
+    /// 通过 `/pin` 固定的消息，每次请求时作为系统消息附加在系统提示词之后
+    pub pins: Vec<String>,
+    /// 自定义的用户提示符，None 时使用默认的 `你:`
+    pub user_label: Option<String>,
+    /// 输入提示符的格式，可以包含 `{model}` 和 `{count}`，None 时只显示用户提示符
+    pub prompt_format: Option<String>,
+    /// 自定义的回复标签，None 时使用默认的 `AI:`
+    pub assistant_label: Option<String>,
+    /// 是否以当前模型的名称作为回复标签
+    pub show_model_label: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            stream_delay_ms: 10,
+            debug: false,
+            markdown: false,
+            pipe_command: None,
+            stream_to_file: None,
+            strip_prefix: None,
+            client_stop: None,
+            hooks: Hooks::default(),
+            fallbacks: BTreeMap::new(),
+            raw_sse: false,
+            reconnect_attempts: 0,
+            keep_reasoning: false,
+            max_reasoning_chars: 0,
+            hide_reasoning: false,
+            only_reasoning: false,
+            buffered: false,
+            thinking_label: None,
+            esc_timeout_ms: 500,
+            confirm_destructive: true,
+            verify_model: true,
+            http_version: None,
+            max_redirects: 5,
+            idle_clear_mins: None,
+            input_history: true,
+            autosave_dir: None,
+            autotitle: false,
+            dump_requests: None,
+            extra_headers: Vec::new(),
+            separators: false,
+            pager: false,
+            timestamps: false,
+            completions: 1,
+            seed: None,
+            modes: builtin_modes(),
+            mode: None,
+            budget: None,
+            context_window: None,
+            input_warn_chars: None,
+            auto_compact_tokens: None,
+            profile: None,
+            model: DEFAULT_MODEL.to_string(),
+            system_prompt: None,
+            system_prompt_override: None,
+            pins: Vec::new(),
+            user_label: None,
+            prompt_format: None,
+            assistant_label: None,
+            show_model_label: false,
+        }
+    }
+}
+
+impl Settings {
+    /// 按 默认值 -> 环境变量 -> 命令行参数 的优先级构造设置
+    pub fn load(cli: &Cli) -> Self {
+        let mut settings = Self::default();
+        settings.apply_env();
+        settings.apply_cli(cli);
+        settings
+    }
+
+    fn apply_env(&mut self) {
+        if let Some(delay) = env::var("HAMBUR_STREAM_DELAY_MS").ok().and_then(|v| v.parse().ok()) {
+            self.stream_delay_ms = delay;
+        }
+        if env::var("HAMBUR_DEBUG").is_ok() {
+            self.debug = true;
+        }
+        if let Some(attempts) = env::var("HAMBUR_RECONNECT_ATTEMPTS").ok().and_then(|v| v.parse().ok()) {
+            self.reconnect_attempts = attempts;
+        }
+        if let Some(max) = env::var("HAMBUR_MAX_REASONING_CHARS").ok().and_then(|v| v.parse().ok()) {
+            self.max_reasoning_chars = max;
+        }
+        if env::var("HAMBUR_HIDE_REASONING").is_ok() {
+            self.hide_reasoning = true;
+        }
+        if let Ok(label) = env::var("HAMBUR_THINKING_LABEL") {
+            self.thinking_label = Some(label);
+        }
+        if let Some(timeout) = env::var("HAMBUR_ESC_TIMEOUT_MS").ok().and_then(|v| v.parse().ok()) {
+            self.esc_timeout_ms = timeout;
+        }
+        if env::var("HAMBUR_NO_CONFIRM").is_ok() {
+            self.confirm_destructive = false;
+        }
+        if env::var("HAMBUR_NO_MODEL_CHECK").is_ok() {
+            self.verify_model = false;
+        }
+        if let Some(seed) = env::var("HAMBUR_SEED").ok().and_then(|v| v.parse().ok()) {
+            self.seed = Some(seed);
+        }
+        if let Some(budget) = env::var("HAMBUR_BUDGET").ok().and_then(|v| v.parse().ok()) {
+            self.budget = Some(budget);
+        }
+        if let Some(limit) = env::var("HAMBUR_INPUT_WARN_CHARS").ok().and_then(|v| v.parse().ok()) {
+            self.input_warn_chars = Some(limit);
+        }
+        if let Some(threshold) = env::var("HAMBUR_AUTO_COMPACT_TOKENS").ok().and_then(|v| v.parse().ok()) {
+            self.auto_compact_tokens = Some(threshold);
+        }
+        if let Ok(profile) = env::var("HAMBUR_PROFILE") {
+            self.profile = Some(profile);
+        }
+        if let Ok(prefix) = env::var("HAMBUR_STRIP_PREFIX") && !prefix.is_empty() {
+            self.strip_prefix = Some(prefix);
+        }
+        if let Ok(stop) = env::var("HAMBUR_CLIENT_STOP") && !stop.is_empty() {
+            self.client_stop = Some(stop);
+        }
+        if let Some(version) = env::var("HAMBUR_HTTP_VERSION").ok().and_then(|v| v.parse().ok()) {
+            self.http_version = Some(version);
+        }
+        if let Some(max) = env::var("HAMBUR_MAX_REDIRECTS").ok().and_then(|v| v.parse().ok()) {
+            self.max_redirects = max;
+        }
+        if let Some(mins) = env::var("HAMBUR_IDLE_CLEAR_MINS").ok().and_then(|v| v.parse().ok()) {
+            self.idle_clear_mins = (mins > 0).then_some(mins);
+        }
+        if env::var("HAMBUR_NO_INPUT_HISTORY").is_ok() {
+            self.input_history = false;
+        }
+        if let Ok(path) = env::var("HAMBUR_DUMP_REQUESTS") && !path.is_empty() {
+            self.dump_requests = Some(paths::expand_home(&path));
+        }
+        // 未指定目录时保存到缓存目录下的 sessions 中
+        if let Ok(dir) = env::var("HAMBUR_AUTOSAVE") {
+            self.autosave_dir = if dir.is_empty() {
+                paths::cache_dir().map(|cache| cache.join("sessions"))
+            } else {
+                Some(paths::expand_home(&dir))
+            };
+        }
+        if env::var("HAMBUR_AUTOTITLE").is_ok() {
+            self.autotitle = true;
+        }
+        if let Ok(format) = env::var("HAMBUR_PROMPT_FORMAT") && !format.is_empty() {
+            self.prompt_format = Some(format);
+        }
+        if env::var("HAMBUR_SEPARATORS").is_ok() {
+            self.separators = true;
+        }
+        if env::var("HAMBUR_PAGER").is_ok() {
+            self.pager = true;
+        }
+    }
+
+    fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(delay) = cli.delay {
+            self.stream_delay_ms = delay;
+        }
+        if cli.debug {
+            self.debug = true;
+        }
+        if cli.markdown {
+            self.markdown = true;
+        }
+        if let Some(command) = &cli.pipe {
+            self.pipe_command = Some(command.clone());
+        }
+        if let Some(path) = &cli.stream_to_file {
+            self.stream_to_file = Some(PathBuf::from(path));
+        }
+        if cli.raw {
+            self.raw_sse = true;
+        }
+        if cli.hide_reasoning {
+            self.hide_reasoning = true;
+        }
+        if cli.only_reasoning {
+            self.only_reasoning = true;
+        }
+        if cli.keep_reasoning {
+            self.keep_reasoning = true;
+        }
+        if let Some(window) = cli.context_window {
+            self.context_window = Some(window);
+        }
+        if cli.show_model_label {
+            self.show_model_label = true;
+        }
+        if let Some(profile) = &cli.profile {
+            self.profile = Some(profile.clone());
+        }
+        if let Some(attempts) = cli.reconnect {
+            self.reconnect_attempts = attempts;
+        }
+        if cli.timestamps {
+            self.timestamps = true;
+        }
+    }
+
+    /// 应用配置文件（已合并选中的配置档）中的默认模型和系统提示词
+    pub fn apply_config(&mut self, config: &Config) {
+        if let Some(model) = &config.default_model {
+            self.model = model.clone();
+        }
+        self.system_prompt = self.system_prompt_override.clone().or_else(|| config.system_prompt.clone());
+        self.modes = builtin_modes();
+        self.modes.extend(config.modes.clone());
+        // 重新加载配置后，当前模式已被删除时恢复默认参数
+        if self.mode.as_ref().is_some_and(|mode| !self.modes.contains_key(mode)) {
+            self.mode = None;
+        }
+        self.user_label = config.user_label.clone();
+        self.assistant_label = config.assistant_label.clone();
+        self.hooks = config.hooks.clone();
+        self.fallbacks = config.fallbacks.clone();
+    }
+
+    /// 附加在每次请求的聊天记录之前、不保存到聊天记录中的消息：系统提示词和固定的消息
+    pub fn preamble(&self) -> Vec<ChatMessage> {
+        self.system_prompt.iter()
+            .chain(&self.pins)
+            .map(|content| ChatMessage::new("system", content.as_str()))
+            .collect()
+    }
+
+    /// 用户输入前的提示符
+    pub fn user_label(&self) -> &str {
+        self.user_label.as_deref().unwrap_or(t(MsgKey::UserPrompt))
+    }
+
+    /// 隐藏推理内容时显示的提示文字，None 表示不显示提示
+    pub fn thinking_label(&self) -> Option<&str> {
+        match self.thinking_label.as_deref() {
+            Some("") => None,
+            Some(label) => Some(label),
+            None => Some(t(MsgKey::Thinking)),
+        }
+    }
+
+    /// 回复前的标签
+    pub fn assistant_label(&self) -> &str {
+        self.assistant_label.as_deref().unwrap_or(t(MsgKey::AssistantLabel))
+    }
+
+    /// 通过 `/set <名称> <值>` 在运行时修改设置
+    /// 名称未知或值无效时返回提示信息
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "n" => match value.parse() {
+                Ok(n) if n >= 1 => self.completions = n,
+                _ => return Err(tf(MsgKey::SetInvalidValue, &[&key, &value])),
+            },
+            "seed" if value == "off" => self.seed = None,
+            "seed" => match value.parse() {
+                Ok(seed) => self.seed = Some(seed),
+                _ => return Err(tf(MsgKey::SetInvalidValue, &[&key, &value])),
+            },
+            _ => return Err(t(MsgKey::SetUsage).to_string()),
+        }
+        Ok(())
+    }
+
+    /// 切换采样预设，返回预设的参数；`default` 表示使用提供商的默认参数
+    pub fn set_mode(&mut self, name: &str) -> Result<SamplingPreset, String> {
+        if name == DEFAULT_MODE {
+            self.mode = None;
+            return Ok(SamplingPreset::default());
+        }
+        match self.modes.get(name) {
+            Some(preset) => {
+                self.mode = Some(name.to_string());
+                Ok(*preset)
+            },
+            None => Err(tf(MsgKey::ModeNotFound, &[&name])),
+        }
+    }
+
+    /// 当前采样预设的参数
+    pub fn sampling(&self) -> SamplingPreset {
+        self.mode.as_ref()
+            .and_then(|mode| self.modes.get(mode))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// 可通过 `/set` 修改的设置及其当前值
+    pub fn settable_values(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("n", self.completions.to_string()),
+            ("seed", self.seed.map_or_else(|| t(MsgKey::ValueUnset).to_string(), |seed| seed.to_string())),
+        ]
+    }
+
+    /// 当前生效的设置及其来源，用于 `/config` 和 `--show-config`
+    /// 依次应用默认值、环境变量、命令行参数、配置文件和固定消息文件，逐项比较每一层的结果，
+    /// 最后改变该值的一层即为来源；与启动时的结果不同的值来自运行时的命令
+    pub fn effective_config(&self, cli: &Cli, config: &Config) -> Vec<(&'static str, String, Source)> {
+        let mut layer = Self::default();
+        let defaults = layer.effective_values();
+        layer.apply_env();
+        let from_env = layer.effective_values();
+        layer.apply_cli(cli);
+        // `--system-prompt-file` 由调用方读取，同样算作命令行参数
+        layer.system_prompt_override = self.system_prompt_override.clone();
+        if layer.system_prompt_override.is_some() {
+            layer.system_prompt = layer.system_prompt_override.clone();
+        }
+        let from_cli = layer.effective_values();
+        layer.apply_config(config);
+        let from_config = layer.effective_values();
+        layer.pins = pins::load();
+        let from_pins = layer.effective_values();
+
+        self.effective_values()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, value))| {
+                let source = if value != from_pins[i].1 {
+                    Source::Runtime
+                } else if from_pins[i].1 != from_config[i].1 {
+                    Source::Pins
+                } else if from_config[i].1 != from_cli[i].1 {
+                    Source::Config
+                } else if from_cli[i].1 != from_env[i].1 {
+                    Source::Cli
+                } else if from_env[i].1 != defaults[i].1 {
+                    Source::Env
+                } else {
+                    Source::Default
+                };
+                (key, value, source)
+            })
+            .collect()
+    }
+
+    /// 用于显示的设置值，请求头中的认证信息不显示
+    fn effective_values(&self) -> Vec<(&'static str, String)> {
+        fn show(value: Option<impl ToString>) -> String {
+            value.map_or_else(|| t(MsgKey::ValueUnset).to_string(), |value| value.to_string())
+        }
+        let system_prompt = self.system_prompt.as_ref().map(|prompt| {
+            let preview: String = prompt.chars().take(40).collect();
+            let ellipsis = if preview.len() < prompt.len() { "…" } else { "" };
+            format!("{:?}{} ({})", preview, ellipsis, tf(MsgKey::CharCount, &[&prompt.chars().count()]))
+        });
+        let headers = self.extra_headers.iter()
+            .map(|(name, value)| if headers::is_secret(name) { format!("{}: ***", name) } else { format!("{}: {}", name, value) })
+            .collect::<Vec<_>>();
+        let http_version = self.http_version.map(|version| match version {
+            HttpVersion::Http1 => "1.1",
+            HttpVersion::Http2 => "2",
+        });
+
+        vec![
+            ("default_model", self.model.clone()),
+            ("profile", show(self.profile.as_ref())),
+            ("system_prompt", show(system_prompt)),
+            ("pins", self.pins.len().to_string()),
+            ("mode", self.mode.clone().unwrap_or_else(|| DEFAULT_MODE.to_string())),
+            ("n", self.completions.to_string()),
+            ("seed", show(self.seed)),
+            ("context_window", show(self.context_window)),
+            ("auto_compact_tokens", show(self.auto_compact_tokens)),
+            ("stream_delay_ms", self.stream_delay_ms.to_string()),
+            ("buffered", self.buffered.to_string()),
+            ("markdown", self.markdown.to_string()),
+            ("pager", self.pager.to_string()),
+            ("separators", self.separators.to_string()),
+            ("timestamps", self.timestamps.to_string()),
+            ("prompt_format", show(self.prompt_format.as_ref())),
+            ("user_label", self.user_label().to_string()),
+            ("assistant_label", self.assistant_label().to_string()),
+            ("show_model_label", self.show_model_label.to_string()),
+            ("hide_reasoning", self.hide_reasoning.to_string()),
+            ("only_reasoning", self.only_reasoning.to_string()),
+            ("keep_reasoning", self.keep_reasoning.to_string()),
+            ("max_reasoning_chars", self.max_reasoning_chars.to_string()),
+            ("strip_prefix", show(self.strip_prefix.as_ref())),
+            ("client_stop", show(self.client_stop.as_ref())),
+            ("pre_send", show(self.hooks.pre_send.as_ref())),
+            ("post_receive", show(self.hooks.post_receive.as_ref())),
+            ("fallbacks", if self.fallbacks.is_empty() {
+                t(MsgKey::ValueUnset).to_string()
+            } else {
+                self.fallbacks.iter().map(|(from, to)| format!("{} -> {}", from, to)).collect::<Vec<_>>().join(", ")
+            }),
+            ("pipe", show(self.pipe_command.as_ref())),
+            ("stream_to_file", show(self.stream_to_file.as_ref().map(|path| path.display()))),
+            ("reconnect", self.reconnect_attempts.to_string()),
+            ("http_version", show(http_version)),
+            ("max_redirects", self.max_redirects.to_string()),
+            ("extra_headers", if headers.is_empty() { t(MsgKey::ValueUnset).to_string() } else { headers.join(", ") }),
+            ("esc_timeout_ms", self.esc_timeout_ms.to_string()),
+            ("idle_clear_mins", show(self.idle_clear_mins)),
+            ("confirm_destructive", self.confirm_destructive.to_string()),
+            ("verify_model", self.verify_model.to_string()),
+            ("input_warn_chars", show(self.input_warn_chars)),
+            ("budget", show(self.budget.map(|budget| format!("${:.2}", budget)))),
+            ("input_history", self.input_history.to_string()),
+            ("autosave", show(self.autosave_dir.as_ref().map(|dir| dir.display()))),
+            ("autotitle", self.autotitle.to_string()),
+            ("dump_requests", show(self.dump_requests.as_ref().map(|path| path.display()))),
+            ("raw", self.raw_sse.to_string()),
+            ("debug", self.debug.to_string()),
+        ]
+    }
+
+    /// 连续两次ESC退出程序的时间窗口
+    pub fn esc_timeout(&self) -> Duration {
+        Duration::from_millis(self.esc_timeout_ms)
+    }
+
+    /// 自动清空聊天记录前允许的空闲时间
+    pub fn idle_clear(&self) -> Option<Duration> {
+        self.idle_clear_mins.map(|mins| Duration::from_secs(mins.saturating_mul(60)))
+    }
+
+    /// 每个字符之间的输出延迟
+    pub fn stream_delay(&self) -> Duration {
+        Duration::from_millis(self.stream_delay_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn flags_override_env_and_defaults() {
+        // 第一项为环境变量设置的延迟，None 表示未设置
+        let cases: [(Option<u64>, &[&str], u64, bool); 4] = [
+            (None, &[], 10, false),
+            (Some(25), &[], 25, false),
+            (Some(25), &["--delay", "0"], 0, false),
+            (None, &["--delay", "5", "--debug"], 5, true),
+        ];
+        for (env_delay, args, delay, debug) in cases {
+            let mut settings = Settings::default();
+            if let Some(env_delay) = env_delay {
+                settings.stream_delay_ms = env_delay;
+            }
+            let cli = Cli::parse_from(std::iter::once("hambur").chain(args.iter().copied()));
+            settings.apply_cli(&cli);
+            assert_eq!((settings.stream_delay_ms, settings.debug), (delay, debug), "env: {:?}, args: {:?}", env_delay, args);
+            assert_eq!(settings.stream_delay(), Duration::from_millis(delay));
+        }
+    }
+}
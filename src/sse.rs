@@ -0,0 +1,180 @@
+use crate::models::{ApiError, ChatResponse, ErrorResponse, parse_responses};
+
+/// SSE数据流中一行的类型
+#[derive(Debug, PartialEq)]
+pub enum SseLine<'a> {
+    /// 空行，事件之间的分隔
+    Blank,
+    /// 以冒号开头的注释，常用作心跳（如 OpenRouter 的 `: OPENROUTER PROCESSING`、`: ping`）
+    Comment,
+    /// `event:`、`id:`、`retry:` 字段，不携带回复数据
+    Field,
+    /// 数据流结束标记 `data: [DONE]`
+    Done,
+    /// `data:` 之后的内容；不是标准SSE格式的行原样作为数据
+    Data(&'a str),
+}
+
+/// 判断一行SSE数据的类型，提供商自定义的结束标记由调用方另外判断
+pub fn classify(line: &str) -> SseLine<'_> {
+    if line.trim().is_empty() {
+        return SseLine::Blank;
+    }
+    if line.starts_with(':') {
+        return SseLine::Comment;
+    }
+    if let Some(data) = line.strip_prefix("data:") {
+        // 冒号后的一个空格不属于数据
+        let data = data.strip_prefix(' ').unwrap_or(data);
+        return if data == "[DONE]" { SseLine::Done } else { SseLine::Data(data) };
+    }
+    if ["event:", "id:", "retry:"].iter().any(|field| line.starts_with(field)) {
+        return SseLine::Field;
+    }
+    SseLine::Data(line)
+}
+
+/// `FrameDecoder` 处理一行后得到的结果
+#[derive(Debug)]
+pub enum Frame {
+    /// 收到 `[DONE]` 或提供商的结束标记，之后的内容一律忽略
+    End,
+    /// 正常的数据块，一行中可能拼接了多个
+    Responses(Vec<ChatResponse>),
+    /// 数据流中途以数据帧形式返回的错误
+    Error(ApiError),
+    /// 不是JSON的数据，作为回复文本
+    Text(String),
+    /// 与下一行拼接后仍无法解析的JSON片段
+    Invalid { message: String, data: String },
+}
+
+/// 将SSE数据行解析为响应，流式显示和一次性读取回复共用
+/// 处理提供商的结束标记，跳过心跳和字段行，解析失败的JSON片段先与下一行拼接后再解析，仍然失败时才报告
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    done_marker: Option<String>,
+    /// 解析失败的JSON片段，等待与下一行拼接
+    pending: Option<String>,
+}
+
+impl FrameDecoder {
+    pub fn new(done_marker: Option<&str>) -> Self {
+        Self { done_marker: done_marker.map(str::to_string), pending: None }
+    }
+
+    /// 处理一行数据，返回的结果按顺序处理；上一行的片段无法拼接时先报告该片段，再处理这一行
+    pub fn push_line(&mut self, line: &str) -> Vec<Frame> {
+        // 自定义的结束标记可以是整行，因此先于跳过字段行判断
+        let kind = classify(line);
+        if kind == SseLine::Done || self.done_marker.as_deref().is_some_and(|marker| marker == line || kind == SseLine::Data(marker)) {
+            return vec![Frame::End];
+        }
+        // 跳过空行、注释（心跳）和 `event:` 等字段，不是标准SSE格式的行尝试直接解析整行
+        let SseLine::Data(data) = kind else {
+            return Vec::new();
+        };
+
+        let mut frames = Vec::new();
+        if let Some(fragment) = self.pending.take() {
+            let joined = format!("{}{}", fragment, data);
+            match parse_frame(&joined) {
+                Some(frame) => return vec![frame],
+                None => {
+                    let message = parse_responses(&joined).err().map(|e| e.to_string()).unwrap_or_default();
+                    frames.push(Frame::Invalid { message, data: fragment });
+                },
+            }
+        }
+
+        match parse_frame(data) {
+            Some(frame) => frames.push(frame),
+            None if !data.starts_with('{') && !data.starts_with('[') => frames.push(Frame::Text(data.to_string())),
+            // 可能是被拆分成多行的JSON，先保留片段，与下一行拼接后再解析
+            None => self.pending = Some(data.to_string()),
+        }
+        frames
+    }
+
+    /// 数据流结束时仍未拼接完整的片段
+    pub fn finish(&mut self) -> Option<Frame> {
+        let fragment = self.pending.take()?;
+        let e = parse_responses(&fragment).err()?;
+        Some(Frame::Invalid { message: e.to_string(), data: fragment })
+    }
+
+    /// 重新连接后数据流从头开始，丢弃断开前的片段
+    pub fn reset(&mut self) {
+        self.pending = None;
+    }
+}
+
+/// 将数据解析为数据块或错误帧，部分提供商会在数据流中途以数据帧的形式返回错误，而不是HTTP状态码
+fn parse_frame(data: &str) -> Option<Frame> {
+    match parse_responses(data) {
+        Ok(responses) => Some(Frame::Responses(responses)),
+        Err(_) => serde_json::from_str::<ErrorResponse>(data).ok().map(|error| Frame::Error(error.error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_lines() {
+        let cases = [
+            (": ping", SseLine::Comment),
+            (": keep-alive", SseLine::Comment),
+            (": OPENROUTER PROCESSING", SseLine::Comment),
+            ("", SseLine::Blank),
+            ("   ", SseLine::Blank),
+            ("data: [DONE]", SseLine::Done),
+            ("event: message", SseLine::Field),
+            ("id: 42", SseLine::Field),
+            ("data: {\"choices\":[]}", SseLine::Data("{\"choices\":[]}")),
+            ("data:{\"choices\":[]}", SseLine::Data("{\"choices\":[]}")),
+            ("{\"choices\":[]}", SseLine::Data("{\"choices\":[]}")),
+            ("plain text", SseLine::Data("plain text")),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(classify(line), expected, "line: {:?}", line);
+        }
+    }
+
+    fn describe(frames: Vec<Frame>) -> Vec<String> {
+        frames.into_iter()
+            .map(|frame| match frame {
+                Frame::End => "end".to_string(),
+                Frame::Responses(responses) => responses.iter()
+                    .flat_map(|response| &response.choices)
+                    .filter_map(|choice| choice.content().cloned())
+                    .collect(),
+                Frame::Error(error) => format!("error {}", error),
+                Frame::Text(text) => format!("text {}", text),
+                Frame::Invalid { data, .. } => format!("invalid {}", data),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_lines_in_order() {
+        let mut decoder = FrameDecoder::new(Some("data: END"));
+        let cases: [(&str, &[&str]); 8] = [
+            (": ping", &[]),
+            (r#"data: {"choices":[{"index":0,"delta":{"content":"Hel"}}]}"#, &["Hel"]),
+            (r#"data: {"choices":[{"index":0,"delta""#, &[]),
+            (r#"data: :{"content":"lo"}}]}"#, &["lo"]),
+            ("data: plain", &["text plain"]),
+            (r#"data: {"broken""#, &[]),
+            (r#"data: {"error":{"message":"quota"}}"#, &[r#"invalid {"broken""#, "error quota"]),
+            ("data: END", &["end"]),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(describe(decoder.push_line(line)), expected, "line: {:?}", line);
+        }
+        assert!(decoder.finish().is_none());
+        decoder.push_line(r#"data: {"choices":"#);
+        assert!(matches!(decoder.finish(), Some(Frame::Invalid { .. })));
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::Result;
+use crossterm::{cursor::MoveLeft, execute, style::Print, terminal::{Clear, ClearType}};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
+
+use crate::color::Paint;
+use crate::i18n::{MsgKey, tf};
+
+/// 刷新文件和更新进度的间隔
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 将回复正文直接追加到文件中，不在内存中累积，`--stream-to-file` 使用
+/// stderr 是终端时在光标处显示 `[已接收 N 字符]`，按固定间隔刷新文件并原地更新
+pub struct FileSink {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// 打开时文件已有内容的长度，本次回复从这里开始
+    start: u64,
+    chars: usize,
+    last_report: Instant,
+    /// 当前显示的进度的宽度，0 表示没有显示
+    width: usize,
+}
+
+impl FileSink {
+    /// 以追加方式打开文件，文件不存在时创建
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let start = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            start,
+            chars: 0,
+            last_report: Instant::now(),
+            width: 0,
+        })
+    }
+
+    /// 写入一段正文，距离上次刷新超过间隔时刷新文件并更新进度
+    pub fn write(&mut self, text: &str) -> Result<()> {
+        self.writer.write_all(text.as_bytes())?;
+        self.chars += text.chars().count();
+        if self.last_report.elapsed() >= REPORT_INTERVAL {
+            self.writer.flush()?;
+            self.report()?;
+            self.last_report = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// 刷新后读回本次回复已写入的内容，用于断线重连时让模型接着输出；文件中原有的内容不读回
+    pub fn read_back(&mut self) -> Result<String> {
+        self.writer.flush()?;
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.start))?;
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        Ok(text)
+    }
+
+    /// 刷新剩余内容并清除进度，返回写入的字符数
+    pub fn finish(mut self) -> Result<usize> {
+        self.writer.flush()?;
+        self.erase()?;
+        Ok(self.chars)
+    }
+
+    fn report(&mut self) -> Result<()> {
+        if !io::stderr().is_terminal() {
+            return Ok(());
+        }
+        let text = tf(MsgKey::StreamFileProgress, &[&self.chars]);
+        self.erase()?;
+        execute!(io::stderr(), Print(text.as_str().dim()))?;
+        self.width = text.width();
+        Ok(())
+    }
+
+    fn erase(&mut self) -> Result<()> {
+        if self.width > 0 {
+            execute!(io::stderr(), MoveLeft(self.width as u16), Clear(ClearType::UntilNewLine))?;
+            self.width = 0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_back_skips_existing_content() {
+        let path = std::env::temp_dir().join(format!("hambur-stream-file-{}.txt", std::process::id()));
+        std::fs::write(&path, "earlier reply\n").unwrap();
+        let mut sink = FileSink::open(&path).unwrap();
+        sink.write("partial").unwrap();
+        assert_eq!(sink.read_back().unwrap(), "partial");
+        sink.finish().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "earlier reply\npartial");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
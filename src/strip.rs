@@ -0,0 +1,154 @@
+/// 去掉回复开头多余的前缀（例如模型自行输出的 `Assistant:`）
+/// 数据流中的前缀可能被拆分在多个数据块中，因此先缓冲开头的内容，
+/// 直到能确定是否以前缀开头后再一次性返回，之后的内容原样通过
+#[derive(Debug)]
+pub struct PrefixStripper {
+    prefix: String,
+    pending: String,
+    /// 已经确定是否匹配，之后不再处理
+    done: bool,
+    /// 刚去掉前缀，还需要去掉紧跟其后的空白
+    trim_leading: bool,
+}
+
+impl PrefixStripper {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            pending: String::new(),
+            done: false,
+            trim_leading: false,
+        }
+    }
+
+    /// 追加一段内容，返回可以输出的部分
+    pub fn push(&mut self, content: &str) -> String {
+        if self.done {
+            if !self.trim_leading {
+                return content.to_string();
+            }
+            let rest = content.trim_start();
+            self.trim_leading = rest.is_empty();
+            return rest.to_string();
+        }
+
+        self.pending.push_str(content);
+        let start = self.pending.trim_start();
+        if let Some(rest) = start.strip_prefix(self.prefix.as_str()) {
+            let rest = rest.trim_start().to_string();
+            self.done = true;
+            self.trim_leading = rest.is_empty();
+            self.pending.clear();
+            return rest;
+        }
+        if self.prefix.starts_with(start) {
+            // 目前收到的内容仍可能是前缀的开头
+            return String::new();
+        }
+        self.done = true;
+        std::mem::take(&mut self.pending)
+    }
+
+    /// 数据流结束时返回缓冲中剩余的内容
+    pub fn finish(&mut self) -> String {
+        self.done = true;
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// 在客户端检测停止标记，用于不遵循服务端 `stop` 参数的模型
+/// 标记可能被拆分在多个数据块中，末尾可能是标记开头的内容先缓冲，确定不匹配后再返回
+#[derive(Debug)]
+pub struct StopMatcher {
+    pattern: String,
+    pending: String,
+    /// 已经遇到停止标记，之后的内容全部丢弃
+    stopped: bool,
+}
+
+impl StopMatcher {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            pending: String::new(),
+            stopped: false,
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// 追加一段内容，返回可以输出的部分，不包括停止标记本身
+    pub fn push(&mut self, content: &str) -> String {
+        if self.stopped {
+            return String::new();
+        }
+
+        self.pending.push_str(content);
+        if let Some(position) = self.pending.find(self.pattern.as_str()) {
+            self.stopped = true;
+            self.pending.truncate(position);
+            return std::mem::take(&mut self.pending);
+        }
+        // 保留末尾可能是停止标记开头的部分
+        let keep = self.pattern.char_indices()
+            .skip(1)
+            .map(|(end, _)| &self.pattern[..end])
+            .filter(|head| self.pending.ends_with(head))
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let rest = self.pending.split_off(self.pending.len() - keep);
+        std::mem::replace(&mut self.pending, rest)
+    }
+
+    /// 数据流结束时返回缓冲中剩余的内容
+    pub fn finish(&mut self) -> String {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// 对回复正文依次去掉开头的前缀、在停止标记处截断，流式显示和一次性读取回复共用
+#[derive(Debug, Default)]
+pub struct ContentFilter {
+    stripper: Option<PrefixStripper>,
+    stop: Option<StopMatcher>,
+}
+
+impl ContentFilter {
+    pub fn new(strip_prefix: Option<&str>, client_stop: Option<&str>) -> Self {
+        Self {
+            stripper: strip_prefix.map(PrefixStripper::new),
+            stop: client_stop.map(StopMatcher::new),
+        }
+    }
+
+    /// 追加一段正文，返回可以输出的部分
+    pub fn push(&mut self, content: &str) -> String {
+        let content = match self.stripper.as_mut() {
+            Some(stripper) => stripper.push(content),
+            None => content.to_string(),
+        };
+        match self.stop.as_mut() {
+            Some(stop) => stop.push(&content),
+            None => content,
+        }
+    }
+
+    /// 是否已经遇到停止标记，之后的内容全部丢弃
+    pub fn is_stopped(&self) -> bool {
+        self.stop.as_ref().is_some_and(StopMatcher::is_stopped)
+    }
+
+    /// 数据流结束时返回缓冲中剩余的内容：回复比前缀还短时原样返回，
+    /// 末尾与停止标记开头相同但没有构成完整标记的内容同样返回
+    pub fn finish(&mut self) -> String {
+        let mut rest = self.stripper.as_mut().map(PrefixStripper::finish).unwrap_or_default();
+        if let Some(stop) = self.stop.as_mut() {
+            rest = stop.push(&rest);
+            rest.push_str(&stop.finish());
+        }
+        rest
+    }
+}
@@ -0,0 +1,82 @@
+use crate::i18n::{MsgKey, tf};
+use crate::models::ChatMessage;
+
+/// 一个标签页：独立的聊天记录和当前模型
+pub struct Tab {
+    pub name: String,
+    pub history: Vec<ChatMessage>,
+    pub model: String,
+}
+
+/// 交互模式中同时进行的多个对话
+/// 当前标签页的聊天记录和模型由交互循环直接持有，对应位置只保存名称，切换时两者交换
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    active: usize,
+}
+
+/// 只有一个默认标签页，聊天记录和模型由调用方持有
+impl Default for Tabs {
+    fn default() -> Self {
+        Tabs {
+            tabs: vec![Tab { name: default_name(1), history: Vec::new(), model: String::new() }],
+            active: 0,
+        }
+    }
+}
+
+impl Tabs {
+    /// 当前标签页的序号（从0开始）和名称
+    pub fn active(&self) -> (usize, &str) {
+        (self.active, &self.tabs[self.active].name)
+    }
+
+    /// 新建一个空的标签页并切换过去，新标签页沿用当前模型，返回其序号（从0开始）
+    pub fn open(&mut self, name: Option<String>, history: &mut Vec<ChatMessage>, model: &mut String) -> usize {
+        let name = name.unwrap_or_else(|| default_name(self.tabs.len() + 1));
+        self.tabs.push(Tab { name, history: Vec::new(), model: model.clone() });
+        let index = self.tabs.len() - 1;
+        self.switch(index, history, model);
+        index
+    }
+
+    /// 切换到指定序号（从0开始）的标签页，序号超出范围时返回 false
+    pub fn switch(&mut self, index: usize, history: &mut Vec<ChatMessage>, model: &mut String) -> bool {
+        if index >= self.tabs.len() {
+            return false;
+        }
+        let current = &mut self.tabs[self.active];
+        std::mem::swap(&mut current.history, history);
+        std::mem::swap(&mut current.model, model);
+
+        self.active = index;
+        let next = &mut self.tabs[index];
+        std::mem::swap(&mut next.history, history);
+        std::mem::swap(&mut next.model, model);
+        true
+    }
+
+    /// 按顺序列出所有标签页的名称、聊天记录和模型，当前标签页使用调用方持有的内容
+    pub fn entries<'a>(&'a self, history: &'a [ChatMessage], model: &'a str) -> Vec<(&'a str, &'a [ChatMessage], &'a str)> {
+        self.tabs.iter().enumerate()
+            .map(|(index, tab)| if index == self.active {
+                (tab.name.as_str(), history, model)
+            } else {
+                (tab.name.as_str(), tab.history.as_slice(), tab.model.as_str())
+            })
+            .collect()
+    }
+
+    /// 清空其他标签页的聊天记录，当前标签页的聊天记录由调用方清空
+    pub fn clear_inactive(&mut self) {
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            if index != self.active {
+                tab.history.clear();
+            }
+        }
+    }
+}
+
+fn default_name(number: usize) -> String {
+    tf(MsgKey::TabDefaultName, &[&number])
+}
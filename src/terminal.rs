@@ -1,5 +1,13 @@
 use anyhow::Result;
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use crossterm::{cursor::{MoveLeft, MoveToColumn},
+                event::{read, Event, KeyCode, KeyEvent, KeyEventKind},
+                execute,
+                style::Print,
+                terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen}};
+use std::io::{self, IsTerminal};
+use unicode_width::UnicodeWidthStr;
+
+use crate::color::{Paint, Reset};
 
 /// 使用RAII模式管理终端的原始模式
 /// 在创建时启用原始模式，在作用域结束时自动禁用原始模式
@@ -19,4 +27,129 @@ impl Drop for RawModeGuard {
         // 忽略可能的错误，因为在drop实现中无法返回错误
         let _ = disable_raw_mode();
     }
-}
\ No newline at end of file
+}
+
+/// 使用RAII模式管理终端的备用屏幕
+/// 在创建时切换到备用屏幕，在作用域结束时自动返回主屏幕并恢复原有内容
+pub struct AlternateScreenGuard;
+
+impl AlternateScreenGuard {
+    /// 进入备用屏幕并返回一个守卫实例
+    pub fn enter() -> Result<Self> {
+        execute!(io::stderr(), EnterAlternateScreen)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for AlternateScreenGuard {
+    fn drop(&mut self) {
+        // 忽略可能的错误，因为在drop实现中无法返回错误
+        let _ = execute!(io::stderr(), LeaveAlternateScreen);
+    }
+}
+
+/// 同时管理备用屏幕和原始模式，供分页器、全屏菜单等界面使用
+/// 先进入备用屏幕再启用原始模式，丢弃时按相反顺序恢复
+pub struct FullScreenGuard {
+    _raw: RawModeGuard,
+    _screen: AlternateScreenGuard,
+}
+
+impl FullScreenGuard {
+    /// 进入备用屏幕和原始模式并返回一个守卫实例
+    pub fn enter() -> Result<Self> {
+        let screen = AlternateScreenGuard::enter()?;
+        let raw = RawModeGuard::enter()?;
+        Ok(Self { _raw: raw, _screen: screen })
+    }
+}
+
+/// 在作用域结束时重置终端颜色
+/// 无论流式输出正常结束、被中断还是因错误提前返回，都不会留下未重置的颜色
+pub struct ColorResetGuard;
+
+impl Drop for ColorResetGuard {
+    fn drop(&mut self) {
+        // 被重定向时不写入控制符
+        if io::stdout().is_terminal() {
+            let _ = execute!(io::stdout(), Reset);
+        }
+        if io::stderr().is_terminal() {
+            let _ = execute!(io::stderr(), Reset);
+        }
+    }
+}
+
+/// 隐藏推理内容时在stderr中显示的占位提示，例如 `[思考中...]`，省略号循环变化
+/// 提示显示在光标当前位置，重绘和清除时按显示宽度退回，不影响同一行前面的标签
+/// 丢弃时自动清除，因错误提前返回时也不会留在屏幕上
+pub struct ThinkingIndicator {
+    label: String,
+    frame: usize,
+    /// 当前显示的提示的宽度，0 表示没有显示
+    width: usize,
+}
+
+impl ThinkingIndicator {
+    pub fn new(label: &str) -> Self {
+        Self { label: label.to_string(), frame: 0, width: 0 }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.width > 0
+    }
+
+    /// 显示提示或切换到下一帧
+    pub fn tick(&mut self) -> Result<()> {
+        let text = format!("[{}{:<3}]", self.label, ".".repeat(self.frame % 3 + 1));
+        self.erase()?;
+        execute!(io::stderr(), Print(text.as_str().dim()))?;
+        self.width = text.width();
+        self.frame += 1;
+        Ok(())
+    }
+
+    /// 清除提示，光标回到提示开始的位置
+    pub fn clear(&mut self) -> Result<()> {
+        self.erase()?;
+        self.width = 0;
+        Ok(())
+    }
+
+    fn erase(&self) -> Result<()> {
+        if self.width > 0 {
+            execute!(io::stderr(), MoveLeft(self.width as u16), Clear(ClearType::UntilNewLine))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ThinkingIndicator {
+    fn drop(&mut self) {
+        // 忽略可能的错误，因为在drop实现中无法返回错误
+        let _ = self.clear();
+    }
+}
+
+/// 显示确认提示并在原始模式下读取单个按键
+/// 只有按下 y/Y 时返回true，其他任意键均视为取消；提示和回显写入stderr，不混入重定向的回答
+pub fn confirm(prompt: &str) -> Result<bool> {
+    execute!(io::stderr(),
+        MoveToColumn(0),
+        Print(prompt.yellow())
+    )?;
+
+    let _raw_guard = RawModeGuard::enter()?;
+    let answer = loop {
+        if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) = read()? {
+            break matches!(code, KeyCode::Char('y') | KeyCode::Char('Y'));
+        }
+    };
+
+    execute!(io::stderr(),
+        Print(if answer { "y" } else { "n" }),
+        Print("\n"),
+        MoveToColumn(0)
+    )?;
+    Ok(answer)
+}